@@ -0,0 +1,253 @@
+// builds a level the same way the game does and prints it, for tuning generators without a window
+//
+// cargo run --bin mapgen -- [--seed N] [--depth N] [--recipe NAME] [--size WIDTHxHEIGHT]
+//                           [--history] [--list]
+
+use pathfinding::prelude::bfs;
+
+use rs_granite::{
+    game_logic::map::{
+        builder::BuiltMap,
+        game_map::{GameMap, GameMapTiles2D, GameTile},
+        map_creation::{MAP_HEIGHT, MAP_WIDTH},
+        pathfinding::is_walkable,
+        recipes::map_recipes,
+        tile_definitions::Terrain,
+    },
+    rng::GameRNG,
+};
+
+struct Options {
+    seed: u64,
+    depth: i32,
+    recipe: Option<String>,
+    width: usize,
+    height: usize,
+    show_history: bool,
+    list_recipes: bool,
+}
+
+fn usage() -> ! {
+    eprintln!(
+        "usage: mapgen [--seed N] [--depth N] [--recipe NAME] [--size WIDTHxHEIGHT] [--history] [--list]"
+    );
+    std::process::exit(1);
+}
+
+fn parse_options() -> Options {
+    let mut options = Options {
+        seed: rand::random::<u64>(),
+        depth: 1,
+        recipe: None,
+        width: MAP_WIDTH,
+        height: MAP_HEIGHT,
+        show_history: false,
+        list_recipes: false,
+    };
+
+    let mut args = std::env::args().skip(1);
+
+    while let Some(arg) = args.next() {
+        match arg.as_str() {
+            "--seed" => {
+                options.seed = args
+                    .next()
+                    .and_then(|v| v.parse().ok())
+                    .unwrap_or_else(|| usage())
+            }
+            "--depth" => {
+                options.depth = args
+                    .next()
+                    .and_then(|v| v.parse().ok())
+                    .unwrap_or_else(|| usage())
+            }
+            "--recipe" => options.recipe = Some(args.next().unwrap_or_else(|| usage())),
+            "--size" => {
+                let size = args.next().unwrap_or_else(|| usage());
+
+                match size
+                    .split_once('x')
+                    .and_then(|(w, h)| Some((w.parse().ok()?, h.parse().ok()?)))
+                {
+                    Some((width, height)) if width >= 3 && height >= 3 => {
+                        options.width = width;
+                        options.height = height;
+                    }
+                    _ => usage(),
+                }
+            }
+            "--history" => options.show_history = true,
+            "--list" => options.list_recipes = true,
+            _ => usage(),
+        }
+    }
+
+    options
+}
+
+// the tile's own glyph when it's plain ascii, otherwise something close enough for a terminal
+fn tile_char(tile: GameTile) -> char {
+    match char::from_u32(tile.get_char_rep() as u32) {
+        Some(ch) if ch.is_ascii_graphic() || ch == ' ' => ch,
+        _ if tile.terrain() == Terrain::DeepWater => '=',
+        _ if tile.terrain() == Terrain::Lava => '^',
+        _ if tile.terrain() == Terrain::Chasm => ':',
+        _ if tile == GameTile::DOWN_STAIRS => '>',
+        _ if tile == GameTile::UP_STAIRS => '<',
+        _ if tile.is_blocker() => '#',
+        _ => '.',
+    }
+}
+
+fn print_tiles(map: &GameMap, tiles: &GameMapTiles2D, spawn: Option<(usize, usize)>) {
+    // y goes up the screen in game, so the top row printed is the highest y
+    for y in (0..map.height).rev() {
+        let row: String = (0..map.width)
+            .map(|x| {
+                if spawn == Some((x, y)) {
+                    '@'
+                } else {
+                    tile_char(tiles[map.xy_idx(x, y)])
+                }
+            })
+            .collect();
+
+        println!("{}", row);
+    }
+}
+
+// steps from the spawn to the closest down stairs, walking through doors as the player would
+fn stairs_distance(map: &GameMap, spawn: (usize, usize)) -> Option<usize> {
+    let path = bfs(
+        &spawn,
+        |&(x, y)| {
+            [(0, 1), (1, 0), (0, -1), (-1, 0)]
+                .iter()
+                .map(|(dx, dy)| (x as i32 + dx, y as i32 + dy))
+                .filter(|(nx, ny)| map.is_within_bounds(*nx, *ny))
+                .map(|(nx, ny)| (nx as usize, ny as usize))
+                .filter(|(nx, ny)| is_walkable(map.tiles[map.xy_idx(*nx, *ny)]))
+                .collect::<Vec<_>>()
+        },
+        |&(x, y)| map.tiles[map.xy_idx(x, y)] == GameTile::DOWN_STAIRS,
+    )?;
+
+    Some(path.len() - 1)
+}
+
+fn print_stats(map: &GameMap, spawn: (usize, usize)) {
+    let floor = map.tiles.iter().filter(|tile| !tile.is_blocker()).count();
+
+    let mut region_sizes = vec![0; map.region_count];
+
+    for region in map.regions.iter().flatten() {
+        region_sizes[*region] += 1;
+    }
+
+    println!(
+        "floor: {} of {} tiles ({:.1}%)",
+        floor,
+        map.tiles.len(),
+        floor as f32 * 100.0 / map.tiles.len() as f32
+    );
+    println!(
+        "regions: {} (largest {} tiles)",
+        map.region_count,
+        region_sizes.iter().max().unwrap_or(&0)
+    );
+    println!(
+        "rooms: {}, corridors: {}, vaults: {}, doors: {}, prefab spawns: {}",
+        map.rooms.len(),
+        map.corridors.len(),
+        map.vaults.len(),
+        map.tiles.iter().filter(|tile| tile.is_door()).count(),
+        map.spawn_markers.len()
+    );
+    let terrain_count = |terrain: Terrain| {
+        map.tiles
+            .iter()
+            .filter(|tile| tile.terrain() == terrain)
+            .count()
+    };
+
+    println!(
+        "shallow water: {}, deep water: {}, lava: {}, chasm: {}",
+        terrain_count(Terrain::ShallowWater),
+        terrain_count(Terrain::DeepWater),
+        terrain_count(Terrain::Lava),
+        terrain_count(Terrain::Chasm)
+    );
+    println!("player spawn: {:?}", spawn);
+
+    match stairs_distance(map, spawn) {
+        Some(distance) => println!("spawn to down stairs: {} steps", distance),
+        None => println!("spawn to down stairs: no reachable down stairs"),
+    }
+}
+
+fn main() {
+    let options = parse_options();
+
+    if options.list_recipes {
+        for recipe in map_recipes().all() {
+            println!(
+                "{} (depth {} to {}, weight {})",
+                recipe.name, recipe.depth.0, recipe.depth.1, recipe.weight
+            );
+        }
+
+        return;
+    }
+
+    // the same steps as creating a level in game, so a seed from the game's log gives the same map.
+    // the recipe is always rolled for, even when one is asked for by name, to keep the rng in step
+    let mut level_rng = GameRNG::seeded(options.seed);
+    let mut recipe = map_recipes().pick(options.depth, &mut level_rng);
+
+    if let Some(name) = &options.recipe {
+        recipe = match map_recipes().get(name) {
+            Some(recipe) => recipe,
+            None => {
+                eprintln!("no recipe named {}, --list shows them all", name);
+                std::process::exit(1);
+            }
+        };
+    }
+
+    println!(
+        "recipe: {}, depth: {}, seed: {}, size: {}x{}",
+        recipe.name, options.depth, options.seed, options.width, options.height
+    );
+
+    // no falling back like the game does, a recipe that can't build is what this is here to show
+    let BuiltMap {
+        map,
+        spawn_position,
+        history,
+    } = match recipe.generate(options.width, options.height, &mut level_rng, true, |_| {}) {
+        Ok(built) => built,
+        Err(err) => {
+            eprintln!("recipe {} failed: {}", recipe.name, err);
+            std::process::exit(1);
+        }
+    };
+    let spawn = (spawn_position.x as usize, spawn_position.y as usize);
+
+    if options.show_history {
+        for (frame_idx, frame) in history.iter().enumerate() {
+            println!(
+                "frame {} of {}: {}",
+                frame_idx + 1,
+                history.len(),
+                frame.label
+            );
+            print_tiles(&map, &frame.tiles, None);
+            println!();
+        }
+    }
+
+    print_tiles(&map, &map.tiles, Some(spawn));
+    println!();
+    println!("history frames: {}", history.len());
+    print_stats(&map, spawn);
+}
@@ -1,16 +1,17 @@
-use bevy::{prelude::*, render::camera::RenderTarget};
+use bevy::{prelude::*, render::camera::RenderTarget, window::WindowResized};
 use iyes_loopless::prelude::*;
 
 use crate::{
     screen::structs::ScreenContext, sprites::SpriteSizes, utils::Point, GameState, InGameState,
 };
 
-pub(crate) struct CameraPlugin;
+pub struct CameraPlugin;
 
 impl Plugin for CameraPlugin {
     fn build(&self, app: &mut App) {
         app.init_resource::<MousePositionOnScreen>()
             .add_startup_system(setup)
+            .add_system(fit_camera_to_window)
             .add_system(process_cursor_movement.run_if(
                 move |cur_state: Res<CurrentState<GameState>>| match cur_state.0 {
                     GameState::InMenu { .. } => true,
@@ -29,6 +30,44 @@ fn setup(mut commands: Commands, sprite_sizes: Res<SpriteSizes>, ctx: Res<Screen
     commands.spawn_bundle(camera_bundle);
 }
 
+// scales the camera so the whole console fits in the window, whole number scales are preferred so
+// the glyphs stay crisp, whatever is left over around the edges is letterboxed by the clear colour
+fn fit_camera_to_window(
+    mut resize_events: EventReader<WindowResized>,
+    wnds: Res<Windows>,
+    ctx: Res<ScreenContext>,
+    sprite_sizes: Res<SpriteSizes>,
+    mut q_camera: Query<(&mut OrthographicProjection, &mut Transform), With<Camera2d>>,
+) {
+    let was_resized = resize_events.iter().count() > 0;
+
+    if !was_resized && !ctx.is_added() && !sprite_sizes.is_changed() {
+        return;
+    }
+
+    let wnd = match wnds.get_primary() {
+        Some(wnd) => wnd,
+        None => return,
+    };
+
+    let console_width = ctx.width as f32 * sprite_sizes.map_sprite_width;
+    let console_height = ctx.height as f32 * sprite_sizes.map_sprite_height;
+
+    let fit_scale = (wnd.width() / console_width).min(wnd.height() / console_height);
+    // if the window is smaller than the console, shrink it down rather than cutting it off
+    let scale = if fit_scale >= 1. {
+        fit_scale.floor()
+    } else {
+        fit_scale
+    };
+
+    for (mut projection, mut transform) in q_camera.iter_mut() {
+        projection.scale = 1. / scale;
+        transform.translation.x = console_width / 2.;
+        transform.translation.y = console_height / 2.;
+    }
+}
+
 #[derive(Default, Debug)]
 pub struct MousePositionOnScreen {
     pub mouse_pos_map_opt: Option<Point>,
@@ -40,6 +79,7 @@ pub fn process_cursor_movement(
     wnds: Res<Windows>,
     mut mouse_res: ResMut<MousePositionOnScreen>,
     sprite_sizes: Res<SpriteSizes>,
+    ctx: Res<ScreenContext>,
     // query to get camera transform
     q_camera: Query<(&Camera, &GlobalTransform)>,
 ) {
@@ -54,8 +94,8 @@ pub fn process_cursor_movement(
         wnds.get_primary().unwrap()
     };
 
-    // check if the cursor is inside the window and get its position
-    if let Some(screen_pos) = wnd.cursor_position() {
+    // the cursor has to be inside the window, and not over the letterboxing around the console
+    let world_pos_opt = wnd.cursor_position().and_then(|screen_pos| {
         // get the size of the window
         let window_size = Vec2::new(wnd.width() as f32, wnd.height() as f32);
 
@@ -65,12 +105,26 @@ pub fn process_cursor_movement(
         // matrix for undoing the projection and camera transform
         let ndc_to_world = camera_transform.compute_matrix() * camera.projection_matrix().inverse();
 
-        // use it to convert ndc to world-space coordinates
-        let world_pos = ndc_to_world.project_point3(ndc.extend(-1.0));
-
-        // reduce it to a 2D value
-        let _world_pos: Vec2 = world_pos.truncate();
+        // use it to convert ndc to world-space coordinates, reduced to a 2D value
+        let world_pos: Vec2 = ndc_to_world.project_point3(ndc.extend(-1.0)).truncate();
+
+        let console_size = Vec2::new(
+            ctx.width as f32 * sprite_sizes.map_sprite_width,
+            ctx.height as f32 * sprite_sizes.map_sprite_height,
+        );
+
+        if world_pos.x < 0.
+            || world_pos.y < 0.
+            || world_pos.x >= console_size.x
+            || world_pos.y >= console_size.y
+        {
+            None
+        } else {
+            Some(world_pos)
+        }
+    });
 
+    if let Some(world_pos) = world_pos_opt {
         let new_mouse_map_x = (world_pos.x / (sprite_sizes.map_sprite_width as f32)).trunc() as i32;
         let new_mouse_map_y =
             (world_pos.y / (sprite_sizes.map_sprite_height as f32)).trunc() as i32;
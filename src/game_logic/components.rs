@@ -1,6 +1,8 @@
 use std::collections::HashSet;
 
 use bevy::prelude::*;
+use serde::Deserialize;
+
 #[derive(Component, Eq, Hash, PartialEq, Clone, Debug)]
 pub struct Position {
     pub x: i32,
@@ -24,9 +26,86 @@ pub struct Monster {}
 #[derive(Component)]
 pub struct Blocker {}
 
+// bumping into a closed door opens it rather than stopping dead
+#[derive(Component)]
+pub struct DoorOpener {}
+
+// deep water is no obstacle and shallow water doesn't slow it down
+#[derive(Component)]
+pub struct Swimmer {}
+
+// passes over water, lava and chasms without touching them
+#[derive(Component)]
+pub struct Flier {}
+
+// lava doesn't hurt it and it never catches fire
+#[derive(Component)]
+pub struct FireImmune {}
+
+// takes damage every turn until it runs out or it gets into water
+#[derive(Component)]
+pub struct Burning {
+    pub turns: u32,
+}
+
+// waded into shallow water, the next move goes on getting out of it
+#[derive(Component)]
+pub struct Slowed {}
+
 #[derive(Component)]
 pub struct Viewshed {
     pub dirty: bool,
     pub distance: u16,
     pub visible_tiles: HashSet<Position>,
 }
+
+#[derive(Component)]
+pub struct Item {}
+
+// can tunnel through diggable walls, each wall takes dig_turns turns of work to bring down
+#[derive(Component)]
+pub struct Digger {
+    pub dig_turns: u32,
+    pub target: Option<Position>,
+    pub progress: u32,
+}
+
+impl Digger {
+    pub fn new(dig_turns: u32) -> Digger {
+        Digger {
+            dig_turns: dig_turns.max(1),
+            target: None,
+            progress: 0,
+        }
+    }
+}
+
+// an item that makes whoever picks it up a digger
+#[derive(Component)]
+pub struct DiggingTool {
+    pub dig_turns: u32,
+}
+
+#[derive(Component, Clone, Debug)]
+pub struct Stats {
+    pub max_hp: i32,
+    pub hp: i32,
+    pub power: i32,
+    pub defense: i32,
+}
+
+#[derive(Clone, Copy, Debug, PartialEq, Eq, Deserialize)]
+#[serde(rename_all = "snake_case")]
+pub enum AiType {
+    // heads straight for the player
+    Chase,
+    // stumbles about at random
+    Wander,
+    // never moves, eg turrets and fungus
+    Static,
+}
+
+#[derive(Component)]
+pub struct Ai {
+    pub ai_type: AiType,
+}
@@ -0,0 +1,114 @@
+use bevy::prelude::*;
+use iyes_loopless::state::NextState;
+
+use crate::{
+    game_logic::{
+        components::{Burning, FireImmune, Flier, Player, Position, Stats},
+        map::{game_map::GameMap, map_creation::change_depth, tile_definitions::Terrain},
+        resources::DungeonLevel,
+    },
+    GameState, InMenuState,
+};
+
+const LAVA_DAMAGE: i32 = 6;
+const BURN_DAMAGE: i32 = 2;
+// how long something keeps burning after it gets out of the lava
+const BURN_TURNS: u32 = 3;
+const FALL_DAMAGE: i32 = 4;
+
+// once a round, after everyone has moved, whatever each creature is standing in does its thing
+pub fn handle_terrain_effects(
+    mut commands: Commands,
+    map: Res<GameMap>,
+    mut dungeon_level: ResMut<DungeonLevel>,
+    // fliers never touch what's below them
+    mut creature_query: Query<
+        (Entity, &Position, &mut Stats, Option<&mut Burning>),
+        Without<Flier>,
+    >,
+    fire_immune_query: Query<(), With<FireImmune>>,
+    player_query: Query<Entity, With<Player>>,
+    level_entity_query: Query<Entity, (With<Position>, Without<Player>)>,
+) {
+    // set when the player leaves the level, the rest of it is about to be thrown away
+    let mut next_depth = None;
+    let mut player_died = false;
+
+    let player_entity = player_query.get_single().ok();
+
+    for (entity, pos, mut stats, burning_opt) in creature_query.iter_mut() {
+        let is_player = Some(entity) == player_entity;
+
+        let terrain = map.terrain_at(pos.x, pos.y);
+
+        match terrain {
+            Terrain::Ground => {}
+            Terrain::ShallowWater | Terrain::DeepWater => {
+                if burning_opt.is_some() {
+                    commands.entity(entity).remove::<Burning>();
+                }
+            }
+            Terrain::Lava => {
+                if !fire_immune_query.contains(entity) {
+                    stats.hp -= LAVA_DAMAGE;
+
+                    commands
+                        .entity(entity)
+                        .insert(Burning { turns: BURN_TURNS });
+                }
+            }
+            Terrain::Chasm => {
+                if is_player {
+                    info!("the player falls into the chasm");
+
+                    stats.hp -= FALL_DAMAGE;
+                    next_depth = Some(dungeon_level.depth + 1);
+                } else {
+                    // gone down to the level below, which doesn't exist yet
+                    commands.entity(entity).despawn_recursive();
+                    continue;
+                }
+            }
+        }
+
+        // lava keeps burning them topped up, the countdown only starts once they're out of it
+        if let Some(mut burning) = burning_opt.filter(|_| terrain == Terrain::Ground) {
+            stats.hp -= BURN_DAMAGE;
+            burning.turns = burning.turns.saturating_sub(1);
+
+            if burning.turns == 0 {
+                commands.entity(entity).remove::<Burning>();
+            }
+        }
+
+        if stats.hp > 0 {
+            continue;
+        }
+
+        if is_player {
+            info!("the player has died");
+
+            stats.hp = 0;
+            player_died = true;
+        } else {
+            commands.entity(entity).despawn_recursive();
+        }
+    }
+
+    // there's no death screen yet, going back to the menu at least ends the run
+    if player_died {
+        commands.insert_resource(NextState(GameState::InMenu {
+            menu_state: InMenuState::MainMenu,
+        }));
+        return;
+    }
+
+    if let Some(depth) = next_depth {
+        change_depth(
+            &mut commands,
+            &mut dungeon_level,
+            depth,
+            level_entity_query.iter(),
+        );
+    }
+}
@@ -1,16 +1,76 @@
-use bevy::{prelude::*, reflect::Map};
-use iyes_loopless::prelude::*;
+use std::fmt;
 
-use crate::{game_logic::components::Position, rng::GameRNG};
+use crate::{game_logic::components::Position, rng::GameRNG, utils::Rectangle};
 
-use super::game_map::{GameMap, GameMapTiles2D, GameTile};
+use super::{
+    game_map::{GameMap, GameTile, MapSnapshot},
+    population::SpawnEntry,
+};
 
 pub type BoxedMapGenerator = Box<dyn MapGenerator>;
 
+pub type MapGenerationResult = Result<GeneratorOutput, MapGenerationError>;
+
+#[derive(Clone, Debug, PartialEq, Eq)]
+pub enum MapGenerationError {
+    // nowhere left to put the player, usually something earlier walled everything in
+    NoFreeSpace,
+    // every stage ran but none of them said where the player starts
+    NoPlayerSpawn,
+    MapTooSmall { width: usize, height: usize },
+    UnknownPrefab(String),
+    WfcSampleTooSmall { pattern_size: usize },
+    WfcContradiction { attempts: usize },
+}
+
+impl fmt::Display for MapGenerationError {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            MapGenerationError::NoFreeSpace => write!(f, "no free floor to spawn the player on"),
+            MapGenerationError::NoPlayerSpawn => write!(f, "no stage placed the player"),
+            MapGenerationError::MapTooSmall { width, height } => {
+                write!(
+                    f,
+                    "a {}x{} map is too small for this generator",
+                    width, height
+                )
+            }
+            MapGenerationError::UnknownPrefab(name) => write!(f, "no prefab named {}", name),
+            MapGenerationError::WfcSampleTooSmall { pattern_size } => write!(
+                f,
+                "wfc sample is smaller than the pattern size of {}",
+                pattern_size
+            ),
+            MapGenerationError::WfcContradiction { attempts } => {
+                write!(f, "wfc hit a contradiction on all {} attempts", attempts)
+            }
+        }
+    }
+}
+
+impl std::error::Error for MapGenerationError {}
+
+// anything a generator wants added to the level besides the tiles it changed, the builder folds
+// it into the map once the generator is done
+#[derive(Default)]
+pub struct GeneratorOutput {
+    pub player_spawn: Option<Position>,
+    pub spawns: Vec<SpawnEntry>,
+    pub rooms: Vec<Rectangle>,
+    pub down_stairs: Vec<(usize, usize)>,
+    pub up_stairs: Vec<(usize, usize)>,
+}
+
+// what a whole chain of generators ends up with
+pub struct BuiltMap {
+    pub map: GameMap,
+    pub spawn_position: Position,
+    pub history: Vec<MapSnapshot>,
+}
+
 pub struct MapBuilder<'a> {
     map: GameMap,
-    player_spawn_position: Position,
-    history: Vec<GameMapTiles2D>,
+    history: Vec<MapSnapshot>,
     rng: &'a mut GameRNG,
 }
 
@@ -18,45 +78,173 @@ impl<'a> MapBuilder<'a> {
     pub fn new(width: usize, height: usize, rng: &mut GameRNG) -> MapBuilder {
         MapBuilder {
             map: GameMap::new(width, height),
-            player_spawn_position: Position { x: 0, y: 0 },
             history: Vec::new(),
-            rng: rng,
+            rng,
         }
     }
 
+    // history is off unless asked for, nothing but the visualisation and mapgen look at it
+    pub fn record_history(&mut self, record: bool) -> &mut MapBuilder<'a> {
+        self.map.record_history = record;
+
+        self
+    }
+
     pub fn with_generator<'b>(
         &'b mut self,
         map_generator: BoxedMapGenerator,
-    ) -> &'b mut MapBuilder<'a> {
-        let mut final_map = map_generator.generate_map(self.get_map(), self.rng);
+    ) -> Result<&'b mut MapBuilder<'a>, MapGenerationError> {
+        let output = map_generator.generate_map(&mut self.map, self.rng)?;
 
-        self.history.append(&mut final_map.history.clone());
+        self.apply_output(output);
 
-        final_map.clear_history();
+        self.history.append(&mut self.map.history);
 
-        self.map = final_map.clone();
+        // regions are always worked out again, any stage could have opened up or closed off space
+        self.map.calculate_regions();
 
-        if let Some(new_player_spawn) = map_generator.get_player_spawn(self.get_map(), self.rng) {
-            self.player_spawn_position = new_player_spawn;
+        Ok(self)
+    }
+
+    fn apply_output(&mut self, output: GeneratorOutput) {
+        if output.player_spawn.is_some() {
+            self.map.player_spawn = output.player_spawn;
         }
 
-        self
+        self.map.spawn_markers.extend(output.spawns);
+        self.map.rooms.extend(output.rooms);
+
+        if output.down_stairs.is_empty() && output.up_stairs.is_empty() {
+            return;
+        }
+
+        for (x, y) in output.down_stairs {
+            let idx = self.map.xy_idx(x, y);
+            self.map.tiles[idx] = GameTile::DOWN_STAIRS;
+        }
+
+        for (x, y) in output.up_stairs {
+            let idx = self.map.xy_idx(x, y);
+            self.map.tiles[idx] = GameTile::UP_STAIRS;
+        }
+
+        self.map.snapshot("Stairs");
     }
 
-    pub fn get_map(&self) -> GameMap {
-        self.map.clone()
+    pub fn finish(self) -> Result<BuiltMap, MapGenerationError> {
+        Ok(BuiltMap {
+            spawn_position: self
+                .map
+                .player_spawn
+                .clone()
+                .ok_or(MapGenerationError::NoPlayerSpawn)?,
+            map: self.map,
+            history: self.history,
+        })
     }
 
-    pub fn get_spawn_position(&self) -> Position {
-        self.player_spawn_position.clone()
+    pub fn get_rooms(&self) -> Vec<Rectangle> {
+        self.map.rooms.clone()
     }
 
-    pub fn get_history(&self) -> Vec<GameMapTiles2D> {
-        self.history.clone()
+    pub fn get_corridors(&self) -> Vec<Vec<(usize, usize)>> {
+        self.map.corridors.clone()
     }
 }
 
 pub trait MapGenerator {
-    fn generate_map(&self, in_map: GameMap, rng: &mut GameRNG) -> GameMap;
-    fn get_player_spawn(&self, in_map: GameMap, rng: &mut GameRNG) -> Option<Position>;
+    fn generate_map(&self, in_map: &mut GameMap, rng: &mut GameRNG) -> MapGenerationResult;
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::game_logic::map::builders::{
+        bsp::BSPRoomMapGenerator,
+        spawns::RandomFreeSpaceSpawn,
+        utils::FillRoomGenerator,
+        wfc::{WaveFunctionCollapseMapGenerator, WfcSample},
+    };
+
+    #[test]
+    fn spawning_in_solid_rock_finds_no_free_space() {
+        let mut rng = GameRNG::seeded(1);
+        let mut builder = MapBuilder::new(20, 20, &mut rng);
+
+        builder
+            .with_generator(FillRoomGenerator::new(GameTile::WALL))
+            .expect("filling can't fail");
+
+        assert_eq!(
+            builder.with_generator(RandomFreeSpaceSpawn::new()).err(),
+            Some(MapGenerationError::NoFreeSpace)
+        );
+    }
+
+    #[test]
+    fn finishing_without_a_spawn_is_an_error() {
+        let mut rng = GameRNG::seeded(1);
+        let mut builder = MapBuilder::new(20, 20, &mut rng);
+
+        builder
+            .with_generator(FillRoomGenerator::new(GameTile::FLOOR))
+            .expect("filling can't fail");
+
+        assert_eq!(
+            builder.finish().err(),
+            Some(MapGenerationError::NoPlayerSpawn)
+        );
+    }
+
+    #[test]
+    fn bsp_on_a_tiny_map_is_too_small() {
+        let mut rng = GameRNG::seeded(1);
+        let mut builder = MapBuilder::new(10, 8, &mut rng);
+
+        assert_eq!(
+            builder.with_generator(BSPRoomMapGenerator::new()).err(),
+            Some(MapGenerationError::MapTooSmall {
+                width: 10,
+                height: 8
+            })
+        );
+    }
+
+    // the partitions here get too narrow to split long before the split depth runs out, which
+    // used to keep retrying the split forever
+    #[test]
+    fn bsp_on_the_smallest_map_gives_up_splitting() {
+        for seed in 0..20 {
+            let mut rng = GameRNG::seeded(seed);
+            let mut builder = MapBuilder::new(14, 14, &mut rng);
+
+            let rooms = builder
+                .with_generator(BSPRoomMapGenerator::new())
+                .expect("big enough for bsp")
+                .get_rooms();
+
+            assert!(rooms.len() > 1, "seed {} was never split", seed);
+        }
+    }
+
+    // noise has hardly any patterns in common with itself, with this seed every attempt paints
+    // itself into a corner
+    #[test]
+    fn wfc_gives_up_after_its_attempts() {
+        let mut rng = GameRNG::seeded(0);
+        let mut map = GameMap::new(12, 12);
+
+        for tile in map.tiles.iter_mut() {
+            if rng.rand_range(0..2) == 0 {
+                *tile = GameTile::WALL;
+            }
+        }
+
+        assert_eq!(
+            WaveFunctionCollapseMapGenerator::new(WfcSample::CurrentMap, 3, 3)
+                .generate_map(&mut map, &mut rng)
+                .err(),
+            Some(MapGenerationError::WfcContradiction { attempts: 3 })
+        );
+    }
 }
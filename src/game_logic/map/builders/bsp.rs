@@ -1,15 +1,25 @@
 use crate::{
-    game_logic::{
-        components::Position,
-        map::{
-            builder::{BoxedMapGenerator, MapGenerator},
-            game_map::{GameMap, GameTile},
+    game_logic::map::{
+        builder::{
+            BoxedMapGenerator, GeneratorOutput, MapGenerationError, MapGenerationResult,
+            MapGenerator,
         },
+        game_map::{GameMap, GameTile},
     },
     rng::GameRNG,
     utils::Rectangle,
 };
 
+// times the map is split in half, giving up to 2^SPLIT_DEPTH rooms
+const SPLIT_DEPTH: usize = 4;
+// narrowest a partition can be and still fit a room
+const MIN_PARTITION_SIZE: usize = 3;
+// four partitions side by side each way plus the outer wall, anything smaller is hardly worth
+// splitting
+const MIN_MAP_SIZE: usize = MIN_PARTITION_SIZE * 4 + 2;
+// tries at a split that keeps both halves in proportion before the partition is left whole
+const SPLIT_ATTEMPTS: usize = 100;
+
 pub struct BSPRoomMapGenerator {}
 
 impl BSPRoomMapGenerator {
@@ -34,10 +44,31 @@ impl BSPPartition {
 }
 
 impl MapGenerator for BSPRoomMapGenerator {
-    fn generate_map(&self, mut in_map: GameMap, rng: &mut GameRNG) -> GameMap {
+    fn generate_map(&self, in_map: &mut GameMap, rng: &mut GameRNG) -> MapGenerationResult {
+        fn draw_corridor(map: &mut GameMap, rect: Rectangle, corridor: &mut Vec<(usize, usize)>) {
+            map.draw_square(
+                rect.x,
+                rect.y,
+                rect.width,
+                rect.height,
+                GameTile::FLOOR,
+                GameTile::FLOOR,
+            );
+
+            corridor.extend(
+                rect.all_squares()
+                    .into_iter()
+                    .filter(|(x, y)| map.is_within_bounds(*x as i32, *y as i32)),
+            );
+        }
+
         fn split_bsp(mut partition: BSPPartition, count: usize, rng: &mut GameRNG) -> BSPPartition {
+            // a partition that can't be split any further just ends up as one bigger room
             if count > 0 {
-                let child_rects = split_random(&partition.rect, rng);
+                let Some(child_rects) = split_random(&partition.rect, rng) else {
+                    return partition;
+                };
+
                 partition.children = Box::new(Some((
                     split_bsp(
                         BSPPartition {
@@ -61,62 +92,83 @@ impl MapGenerator for BSPRoomMapGenerator {
             partition
         }
 
-        fn split_random(rect: &Rectangle, rng: &mut GameRNG) -> (Rectangle, Rectangle) {
-            let dir_rng = rng.rand_range_incl(0..=1);
-
-            // nice rng range is 0.45 - 0.55 rand
-            // so if it's width of 1 -> rect height, that range is
-            // 5% - mid -> 5% + mid
-
-            if dir_rng == 0 {
-                // horizontal
-
-                let left = Rectangle {
-                    x: rect.x,
-                    y: rect.y,
-                    width: rect.width,
-                    height: rng.rand_range_incl(
-                        (0.3 * rect.height as f32) as i32..=(0.7 * rect.height as f32) as i32,
-                    ) as usize,
-                };
+        fn split_random(rect: &Rectangle, rng: &mut GameRNG) -> Option<(Rectangle, Rectangle)> {
+            for _ in 0..SPLIT_ATTEMPTS {
+                let dir_rng = rng.rand_range_incl(0..=1);
+
+                // nice rng range is 0.45 - 0.55 rand
+                // so if it's width of 1 -> rect height, that range is
+                // 5% - mid -> 5% + mid
+
+                if dir_rng == 0 {
+                    // horizontal
+
+                    let left = Rectangle {
+                        x: rect.x,
+                        y: rect.y,
+                        width: rect.width,
+                        height: rng.rand_range_incl(
+                            (0.3 * rect.height as f32) as i32..=(0.7 * rect.height as f32) as i32,
+                        ) as usize,
+                    };
+
+                    let right = Rectangle {
+                        x: rect.x,
+                        y: rect.y + left.height,
+                        width: rect.width,
+                        height: rect.height - left.height,
+                    };
+
+                    if left.height < MIN_PARTITION_SIZE
+                        || right.height < MIN_PARTITION_SIZE
+                        || left.h_ratio() < 0.4
+                        || right.h_ratio() < 0.4
+                    {
+                        continue;
+                    }
 
-                let right = Rectangle {
-                    x: rect.x,
-                    y: rect.y + left.height,
-                    width: rect.width,
-                    height: rect.height - left.height,
-                };
+                    return Some((left, right));
+                } else {
+                    // vertical
+
+                    let top = Rectangle {
+                        x: rect.x,
+                        y: rect.y,
+                        width: rng.rand_range_incl(
+                            (0.3 * rect.width as f32) as i32..=(0.7 * rect.width as f32) as i32,
+                        ) as usize,
+                        height: rect.height,
+                    };
+
+                    let bottom = Rectangle {
+                        x: rect.x + top.width,
+                        y: rect.y,
+                        width: rect.width - top.width,
+                        height: rect.height,
+                    };
+
+                    if top.width < MIN_PARTITION_SIZE
+                        || bottom.width < MIN_PARTITION_SIZE
+                        || top.w_ratio() < 0.4
+                        || bottom.w_ratio() < 0.4
+                    {
+                        continue;
+                    }
 
-                if left.h_ratio() < 0.4 || right.h_ratio() < 0.4 {
-                    return split_random(rect, rng);
+                    return Some((top, bottom));
                 }
+            }
 
-                (left, right)
-            } else {
-                // vertical
-
-                let top = Rectangle {
-                    x: rect.x,
-                    y: rect.y,
-                    width: rng.rand_range_incl(
-                        (0.3 * rect.width as f32) as i32..=(0.7 * rect.width as f32) as i32,
-                    ) as usize,
-                    height: rect.height,
-                };
-
-                let bottom = Rectangle {
-                    x: rect.x + top.width,
-                    y: rect.y,
-                    width: rect.width - top.width,
-                    height: rect.height,
-                };
+            None
+        }
 
-                if top.w_ratio() < 0.4 || bottom.w_ratio() < 0.4 {
-                    return split_random(rect, rng);
-                }
+        let too_small = MapGenerationError::MapTooSmall {
+            width: in_map.width,
+            height: in_map.height,
+        };
 
-                (top, bottom)
-            }
+        if in_map.width < MIN_MAP_SIZE || in_map.height < MIN_MAP_SIZE {
+            return Err(too_small);
         }
 
         let res = split_bsp(
@@ -126,10 +178,14 @@ impl MapGenerator for BSPRoomMapGenerator {
                 width: in_map.width - 2,
                 height: in_map.height - 2,
             }),
-            4,
+            SPLIT_DEPTH,
             rng,
         );
 
+        if res.children.is_none() {
+            return Err(too_small);
+        }
+
         fn collect_leaf(partition: &BSPPartition) -> Vec<Rectangle> {
             let mut res = Vec::new();
             let child = &*partition.children;
@@ -169,11 +225,11 @@ impl MapGenerator for BSPRoomMapGenerator {
                 room_y,
                 room_w,
                 room_h,
-                GameTile::Floor,
-                GameTile::Floor,
+                GameTile::FLOOR,
+                GameTile::FLOOR,
             );
 
-            in_map.snapshot();
+            in_map.snapshot(&format!("BSP: room {}", i + 1));
 
             if i > 0 {
                 let last = &inner_rects[i - 1];
@@ -199,54 +255,64 @@ impl MapGenerator for BSPRoomMapGenerator {
                     }
                 }
 
+                let mut corridor = Vec::new();
+
                 if !x_pairs.is_empty() {
                     let rand_x_pair = x_pairs[rng.rand_range(0..x_pairs.len() as i32) as usize];
 
                     if rand_x_pair.0 .1 < rand_x_pair.1 .1 {
-                        in_map.draw_square(
-                            rand_x_pair.0 .0,
-                            rand_x_pair.0 .1,
-                            1,
-                            rand_x_pair.1 .1 - rand_x_pair.0 .1,
-                            GameTile::Floor,
-                            GameTile::Floor,
+                        draw_corridor(
+                            in_map,
+                            Rectangle {
+                                x: rand_x_pair.0 .0,
+                                y: rand_x_pair.0 .1,
+                                width: 1,
+                                height: rand_x_pair.1 .1 - rand_x_pair.0 .1,
+                            },
+                            &mut corridor,
                         );
                     } else {
-                        in_map.draw_square(
-                            rand_x_pair.1 .0,
-                            rand_x_pair.1 .1,
-                            1,
-                            rand_x_pair.0 .1 - rand_x_pair.1 .1,
-                            GameTile::Floor,
-                            GameTile::Floor,
+                        draw_corridor(
+                            in_map,
+                            Rectangle {
+                                x: rand_x_pair.1 .0,
+                                y: rand_x_pair.1 .1,
+                                width: 1,
+                                height: rand_x_pair.0 .1 - rand_x_pair.1 .1,
+                            },
+                            &mut corridor,
                         );
                     }
 
-                    in_map.snapshot();
+                    in_map.snapshot(&format!("BSP: corridor {} to {}", i, i + 1));
                 } else if !y_pairs.is_empty() {
                     let rand_y_pair = y_pairs[rng.rand_range(0..y_pairs.len() as i32) as usize];
 
                     if rand_y_pair.0 .0 < rand_y_pair.1 .0 {
-                        in_map.draw_square(
-                            rand_y_pair.0 .0,
-                            rand_y_pair.0 .1,
-                            rand_y_pair.1 .0 - rand_y_pair.0 .0,
-                            1,
-                            GameTile::Floor,
-                            GameTile::Floor,
+                        draw_corridor(
+                            in_map,
+                            Rectangle {
+                                x: rand_y_pair.0 .0,
+                                y: rand_y_pair.0 .1,
+                                width: rand_y_pair.1 .0 - rand_y_pair.0 .0,
+                                height: 1,
+                            },
+                            &mut corridor,
                         );
                     } else {
-                        in_map.draw_square(
-                            rand_y_pair.1 .0,
-                            rand_y_pair.1 .1,
-                            rand_y_pair.0 .0 - rand_y_pair.1 .0,
-                            1,
-                            GameTile::Floor,
-                            GameTile::Floor,
+                        draw_corridor(
+                            in_map,
+                            Rectangle {
+                                x: rand_y_pair.1 .0,
+                                y: rand_y_pair.1 .1,
+                                width: rand_y_pair.0 .0 - rand_y_pair.1 .0,
+                                height: 1,
+                            },
+                            &mut corridor,
                         );
                     }
 
-                    in_map.snapshot();
+                    in_map.snapshot(&format!("BSP: corridor {} to {}", i, i + 1));
                 } else {
                     let (cur_rand_x, cur_rand_y) =
                         *&cur_all_squares[rng.rand_range(0..cur_all_squares.len() as i32) as usize];
@@ -259,58 +325,67 @@ impl MapGenerator for BSPRoomMapGenerator {
                         let w = cur_rand_x - last_rand_x + 1;
                         dog_leg_x = last_rand_x + w;
 
-                        in_map.draw_square(
-                            last_rand_x,
-                            last_rand_y,
-                            w,
-                            1,
-                            GameTile::Floor,
-                            GameTile::Floor,
+                        draw_corridor(
+                            in_map,
+                            Rectangle {
+                                x: last_rand_x,
+                                y: last_rand_y,
+                                width: w,
+                                height: 1,
+                            },
+                            &mut corridor,
                         );
                     } else {
                         let w = last_rand_x - cur_rand_x + 1;
                         dog_leg_x = cur_rand_x + w;
 
-                        in_map.draw_square(
-                            cur_rand_x,
-                            cur_rand_y,
-                            w,
-                            1,
-                            GameTile::Floor,
-                            GameTile::Floor,
+                        draw_corridor(
+                            in_map,
+                            Rectangle {
+                                x: cur_rand_x,
+                                y: cur_rand_y,
+                                width: w,
+                                height: 1,
+                            },
+                            &mut corridor,
                         );
                     }
 
                     if last_rand_y < cur_rand_y {
                         // lower, grow up
-                        in_map.draw_square(
-                            dog_leg_x,
-                            last_rand_y,
-                            1,
-                            cur_rand_y - last_rand_y + 1,
-                            GameTile::Floor,
-                            GameTile::Floor,
+                        draw_corridor(
+                            in_map,
+                            Rectangle {
+                                x: dog_leg_x,
+                                y: last_rand_y,
+                                width: 1,
+                                height: cur_rand_y - last_rand_y + 1,
+                            },
+                            &mut corridor,
                         );
                     } else {
-                        in_map.draw_square(
-                            dog_leg_x,
-                            cur_rand_y,
-                            1,
-                            last_rand_y - cur_rand_y + 1,
-                            GameTile::Floor,
-                            GameTile::Floor,
+                        draw_corridor(
+                            in_map,
+                            Rectangle {
+                                x: dog_leg_x,
+                                y: cur_rand_y,
+                                width: 1,
+                                height: last_rand_y - cur_rand_y + 1,
+                            },
+                            &mut corridor,
                         );
                     }
 
-                    in_map.snapshot();
+                    in_map.snapshot(&format!("BSP: corridor {} to {}", i, i + 1));
                 }
+
+                in_map.corridors.push(corridor);
             }
         }
 
-        in_map
-    }
-
-    fn get_player_spawn(&self, in_map: GameMap, rng: &mut GameRNG) -> Option<Position> {
-        None
+        Ok(GeneratorOutput {
+            rooms: inner_rects,
+            ..Default::default()
+        })
     }
 }
@@ -0,0 +1,273 @@
+use serde::Deserialize;
+
+use crate::{
+    game_logic::map::{
+        builder::{BoxedMapGenerator, GeneratorOutput, MapGenerationResult, MapGenerator},
+        game_map::{GameMap, GameTile},
+    },
+    rng::GameRNG,
+};
+
+use super::utils::SymmetricalMapGenerator;
+
+// a snapshot every this many particles, one per particle makes the visualiser crawl
+const SNAPSHOT_INTERVAL: usize = 10;
+
+#[derive(Clone, Copy, Debug, PartialEq, Eq, Deserialize)]
+pub enum DlaMode {
+    // particles wander in from anywhere and stick to the first floor they touch, gives thin
+    // branching tendrils
+    WalkInwards,
+    // particles wander out from the middle through the floor and dig out the first wall they
+    // touch, gives fatter blobs
+    WalkOutwards,
+    // particles head straight for the middle and stick to the first floor they touch, gives
+    // spiky star shapes
+    CentralAttractor,
+}
+
+pub struct DiffusionLimitedAggregationMapGenerator {
+    pub mode: DlaMode,
+    // how many tiles across each particle digs out
+    pub brush_size: usize,
+    // out of 100, how much of the map inside the border should be floor before stopping
+    pub floor_percent: i32,
+    pub horizontal_symmetry: bool,
+    pub vertical_symmetry: bool,
+}
+
+impl DiffusionLimitedAggregationMapGenerator {
+    pub fn new(
+        mode: DlaMode,
+        brush_size: usize,
+        floor_percent: i32,
+        horizontal_symmetry: bool,
+        vertical_symmetry: bool,
+    ) -> BoxedMapGenerator {
+        Box::new(DiffusionLimitedAggregationMapGenerator {
+            mode,
+            brush_size: brush_size.max(1),
+            floor_percent: floor_percent.clamp(0, 100),
+            horizontal_symmetry,
+            vertical_symmetry,
+        })
+    }
+
+    fn is_floor(in_map: &GameMap, (x, y): (i32, i32)) -> bool {
+        !in_map.tiles[in_map.xy_idx(x as usize, y as usize)].is_blocker()
+    }
+
+    fn centre(in_map: &GameMap) -> (i32, i32) {
+        (in_map.width as i32 / 2, in_map.height as i32 / 2)
+    }
+
+    fn random_interior_tile(in_map: &GameMap, rng: &mut GameRNG) -> (i32, i32) {
+        (
+            rng.rand_range(1..in_map.width as i32 - 1),
+            rng.rand_range(1..in_map.height as i32 - 1),
+        )
+    }
+
+    // one step in a random direction, staying put rather than walking onto the border
+    fn wander(in_map: &GameMap, (x, y): (i32, i32), rng: &mut GameRNG) -> (i32, i32) {
+        let (dx, dy) = match rng.rand_range(0..4) {
+            0 => (0, 1),
+            1 => (1, 0),
+            2 => (0, -1),
+            _ => (-1, 0),
+        };
+
+        let (new_x, new_y) = (x + dx, y + dy);
+
+        if new_x < 1
+            || new_y < 1
+            || new_x >= in_map.width as i32 - 1
+            || new_y >= in_map.height as i32 - 1
+        {
+            return (x, y);
+        }
+
+        (new_x, new_y)
+    }
+
+    // digs out the brush around a tile, and wherever it lands when mirrored. returns how many
+    // walls were turned into floor
+    fn paint(&self, in_map: &mut GameMap, (x, y): (i32, i32)) -> usize {
+        let mut dug = 0;
+        let offset = (self.brush_size / 2) as i32;
+
+        for brush_y in 0..self.brush_size as i32 {
+            for brush_x in 0..self.brush_size as i32 {
+                let (paint_x, paint_y) = (x - offset + brush_x, y - offset + brush_y);
+
+                if paint_x < 1
+                    || paint_y < 1
+                    || paint_x >= in_map.width as i32 - 1
+                    || paint_y >= in_map.height as i32 - 1
+                {
+                    continue;
+                }
+
+                for (mirror_x, mirror_y) in SymmetricalMapGenerator::mirrored_positions(
+                    in_map,
+                    self.horizontal_symmetry,
+                    self.vertical_symmetry,
+                    (paint_x as usize, paint_y as usize),
+                ) {
+                    // on odd sized maps the reflection can land on the border
+                    if mirror_x == 0
+                        || mirror_y == 0
+                        || mirror_x >= in_map.width - 1
+                        || mirror_y >= in_map.height - 1
+                    {
+                        continue;
+                    }
+
+                    let idx = in_map.xy_idx(mirror_x, mirror_y);
+
+                    if in_map.tiles[idx].is_blocker() {
+                        in_map.tiles[idx] = GameTile::FLOOR;
+                        dug += 1;
+                    }
+                }
+            }
+        }
+
+        dug
+    }
+
+    // where the particle ends up sticking, or None if it wandered for too long or started on floor
+    fn release_particle(&self, in_map: &GameMap, rng: &mut GameRNG) -> Option<(i32, i32)> {
+        let max_steps = in_map.width * in_map.height * 4;
+
+        match self.mode {
+            DlaMode::WalkInwards => {
+                let mut position =
+                    DiffusionLimitedAggregationMapGenerator::random_interior_tile(in_map, rng);
+
+                if DiffusionLimitedAggregationMapGenerator::is_floor(in_map, position) {
+                    return None;
+                }
+
+                for _ in 0..max_steps {
+                    let next =
+                        DiffusionLimitedAggregationMapGenerator::wander(in_map, position, rng);
+
+                    if DiffusionLimitedAggregationMapGenerator::is_floor(in_map, next) {
+                        return Some(position);
+                    }
+
+                    position = next;
+                }
+
+                None
+            }
+            DlaMode::WalkOutwards => {
+                let mut position = DiffusionLimitedAggregationMapGenerator::centre(in_map);
+
+                // if something else has filled in the middle, start from any floor instead
+                if !DiffusionLimitedAggregationMapGenerator::is_floor(in_map, position) {
+                    let floor: Vec<(i32, i32)> = (0..in_map.tiles.len())
+                        .filter(|idx| !in_map.tiles[*idx].is_blocker())
+                        .map(|idx| ((idx % in_map.width) as i32, (idx / in_map.width) as i32))
+                        .collect();
+
+                    if floor.is_empty() {
+                        return None;
+                    }
+
+                    position = floor[rng.rand_range(0..floor.len() as i32) as usize];
+                }
+
+                for _ in 0..max_steps {
+                    position =
+                        DiffusionLimitedAggregationMapGenerator::wander(in_map, position, rng);
+
+                    if !DiffusionLimitedAggregationMapGenerator::is_floor(in_map, position) {
+                        return Some(position);
+                    }
+                }
+
+                None
+            }
+            DlaMode::CentralAttractor => {
+                let mut position =
+                    DiffusionLimitedAggregationMapGenerator::random_interior_tile(in_map, rng);
+                let centre = DiffusionLimitedAggregationMapGenerator::centre(in_map);
+
+                if DiffusionLimitedAggregationMapGenerator::is_floor(in_map, position) {
+                    return None;
+                }
+
+                while position != centre {
+                    let (dx, dy) = (centre.0 - position.0, centre.1 - position.1);
+
+                    // steps along whichever axis is further off, a rough straight line
+                    let next = if dx.abs() > dy.abs()
+                        || (dx.abs() == dy.abs() && rng.rand_range(0..2) == 0)
+                    {
+                        (position.0 + dx.signum(), position.1)
+                    } else {
+                        (position.0, position.1 + dy.signum())
+                    };
+
+                    if DiffusionLimitedAggregationMapGenerator::is_floor(in_map, next) {
+                        return Some(position);
+                    }
+
+                    position = next;
+                }
+
+                None
+            }
+        }
+    }
+}
+
+impl MapGenerator for DiffusionLimitedAggregationMapGenerator {
+    fn generate_map(&self, in_map: &mut GameMap, rng: &mut GameRNG) -> MapGenerationResult {
+        if in_map.width < 3 || in_map.height < 3 {
+            return Ok(GeneratorOutput::default());
+        }
+
+        let interior_tiles = (in_map.width - 2) * (in_map.height - 2);
+        let target_floor = interior_tiles * self.floor_percent as usize / 100;
+
+        let mut floor_count = in_map
+            .tiles
+            .iter()
+            .filter(|tile| !tile.is_blocker())
+            .count();
+
+        // the particles need something to stick to
+        if floor_count == 0 {
+            let centre = DiffusionLimitedAggregationMapGenerator::centre(in_map);
+
+            floor_count += self.paint(in_map, centre);
+            in_map.snapshot("DLA: seed");
+        }
+
+        let mut particles = 0;
+
+        // gives up eventually if the target can't be reached, say because the brush is bigger than
+        // the gaps left
+        for _ in 0..interior_tiles * 4 {
+            if floor_count >= target_floor {
+                break;
+            }
+
+            if let Some(position) = self.release_particle(in_map, rng) {
+                floor_count += self.paint(in_map, position);
+                particles += 1;
+
+                if particles % SNAPSHOT_INTERVAL == 0 {
+                    in_map.snapshot(&format!("DLA: particle {}", particles));
+                }
+            }
+        }
+
+        in_map.snapshot("DLA: done");
+
+        Ok(GeneratorOutput::default())
+    }
+}
@@ -0,0 +1,73 @@
+use crate::{
+    game_logic::map::{
+        builder::{BoxedMapGenerator, GeneratorOutput, MapGenerationResult, MapGenerator},
+        game_map::{GameMap, GameTile},
+    },
+    rng::GameRNG,
+};
+
+// puts a closed door wherever a corridor leaves a room, needs a generator that records its rooms
+// and corridors to have run first
+pub struct DoorPlacementMapGenerator {}
+
+impl DoorPlacementMapGenerator {
+    pub fn new() -> BoxedMapGenerator {
+        Box::new(DoorPlacementMapGenerator {})
+    }
+}
+
+impl DoorPlacementMapGenerator {
+    fn is_door_candidate(in_map: &GameMap, x: usize, y: usize) -> bool {
+        let (x, y) = (x as i32, y as i32);
+
+        if in_map.tiles[in_map.xy_idx(x as usize, y as usize)] != GameTile::FLOOR
+            || in_map.room_at(x as usize, y as usize).is_some()
+        {
+            return false;
+        }
+
+        let is_wall = |x: i32, y: i32| {
+            !in_map.is_within_bounds(x, y)
+                || in_map.tiles[in_map.xy_idx(x as usize, y as usize)].is_blocker()
+        };
+
+        let in_room = |x: i32, y: i32| {
+            in_map.is_within_bounds(x, y) && in_map.room_at(x as usize, y as usize).is_some()
+        };
+
+        let is_door = |x: i32, y: i32| {
+            in_map.is_within_bounds(x, y)
+                && in_map.tiles[in_map.xy_idx(x as usize, y as usize)] == GameTile::DOOR_CLOSED
+        };
+
+        // doors only make sense in a gap in a wall, and two doors next to each other look odd
+        let in_doorway = (is_wall(x - 1, y)
+            && is_wall(x + 1, y)
+            && (in_room(x, y - 1) || in_room(x, y + 1)))
+            || (is_wall(x, y - 1) && is_wall(x, y + 1) && (in_room(x - 1, y) || in_room(x + 1, y)));
+
+        in_doorway
+            && !is_door(x - 1, y)
+            && !is_door(x + 1, y)
+            && !is_door(x, y - 1)
+            && !is_door(x, y + 1)
+    }
+}
+
+impl MapGenerator for DoorPlacementMapGenerator {
+    fn generate_map(&self, in_map: &mut GameMap, _rng: &mut GameRNG) -> MapGenerationResult {
+        for corridor in in_map.corridors.clone() {
+            for (x, y) in corridor {
+                if DoorPlacementMapGenerator::is_door_candidate(in_map, x, y) {
+                    let idx = in_map.xy_idx(x, y);
+
+                    in_map.tiles[idx] = GameTile::DOOR_CLOSED;
+                }
+            }
+        }
+
+        in_map.snapshot("Doors");
+
+        Ok(GeneratorOutput::default())
+    }
+}
@@ -0,0 +1,407 @@
+use std::collections::VecDeque;
+
+use serde::Deserialize;
+
+use crate::{
+    game_logic::map::{
+        builder::{BoxedMapGenerator, GeneratorOutput, MapGenerationResult, MapGenerator},
+        game_map::{GameMap, GameTile},
+        pathfinding::is_walkable,
+    },
+    rng::GameRNG,
+};
+
+const DIRECTIONS: [(i32, i32); 4] = [(0, 1), (1, 0), (0, -1), (-1, 0)];
+
+// out of this many steps a river takes, one drifts left and one drifts right
+const RIVER_WANDER: i32 = 4;
+// out of 100, how much of a lake is its deep middle
+const LAKE_CORE_PERCENT: usize = 50;
+
+#[derive(Clone, Copy, Debug, PartialEq, Eq, Deserialize)]
+pub enum LiquidKind {
+    Water,
+    Lava,
+    // not a liquid, but it gets laid out the same way
+    Chasm,
+}
+
+impl LiquidKind {
+    fn core_tile(&self) -> GameTile {
+        match self {
+            LiquidKind::Water => GameTile::DEEP_WATER,
+            LiquidKind::Lava => GameTile::LAVA,
+            LiquidKind::Chasm => GameTile::CHASM,
+        }
+    }
+
+    // water gets shallows round the edge, the others drop off straight away
+    fn edge_tile(&self) -> GameTile {
+        match self {
+            LiquidKind::Water => GameTile::SHALLOW_WATER,
+            LiquidKind::Lava => GameTile::LAVA,
+            LiquidKind::Chasm => GameTile::CHASM,
+        }
+    }
+
+    // a ford through water, or a stone bridge over anything else
+    fn crossing_tile(&self) -> GameTile {
+        match self {
+            LiquidKind::Water => GameTile::SHALLOW_WATER,
+            LiquidKind::Lava | LiquidKind::Chasm => GameTile::FLOOR,
+        }
+    }
+}
+
+// left alone by both generators, so nothing important ends up underwater
+fn is_protected(in_map: &GameMap, x: usize, y: usize) -> bool {
+    let tile = in_map.tiles[in_map.xy_idx(x, y)];
+
+    x == 0
+        || y == 0
+        || x >= in_map.width - 1
+        || y >= in_map.height - 1
+        || tile.is_door()
+        || tile == GameTile::DOWN_STAIRS
+        || tile == GameTile::UP_STAIRS
+        || in_map.vault_at(x, y).is_some()
+}
+
+fn is_passable(tile: GameTile) -> bool {
+    !tile.is_blocker() || tile.is_door()
+}
+
+// labels every walkable area with its own id, like the map's regions but treating hazards as walls
+fn walkable_areas(in_map: &GameMap) -> (Vec<Option<usize>>, Vec<usize>) {
+    let mut areas = vec![None; in_map.tiles.len()];
+    let mut sizes = Vec::new();
+
+    for start_idx in 0..in_map.tiles.len() {
+        if areas[start_idx].is_some() || !is_walkable(in_map.tiles[start_idx]) {
+            continue;
+        }
+
+        let area = sizes.len();
+        let mut size = 0;
+        let mut open = VecDeque::from([start_idx]);
+
+        areas[start_idx] = Some(area);
+
+        while let Some(idx) = open.pop_front() {
+            size += 1;
+
+            for next_idx in neighbours(in_map, idx) {
+                if areas[next_idx].is_none() && is_walkable(in_map.tiles[next_idx]) {
+                    areas[next_idx] = Some(area);
+                    open.push_back(next_idx);
+                }
+            }
+        }
+
+        sizes.push(size);
+    }
+
+    (areas, sizes)
+}
+
+fn neighbours(in_map: &GameMap, idx: usize) -> impl Iterator<Item = usize> + '_ {
+    let (x, y) = ((idx % in_map.width) as i32, (idx / in_map.width) as i32);
+
+    DIRECTIONS
+        .iter()
+        .map(move |(dx, dy)| (x + dx, y + dy))
+        .filter(|(x, y)| in_map.is_within_bounds(*x, *y))
+        .map(|(x, y)| in_map.xy_idx(x as usize, y as usize))
+}
+
+// anywhere the liquid cut one walkable area off from the biggest one, the shortest way back across
+// is turned into a ford or bridge. returns whether anything had to change
+fn add_crossings(in_map: &mut GameMap, kind: LiquidKind) -> bool {
+    let mut changed = false;
+
+    loop {
+        let (areas, sizes) = walkable_areas(in_map);
+
+        if sizes.len() <= 1 {
+            return changed;
+        }
+
+        let main_area = (0..sizes.len()).max_by_key(|area| sizes[*area]);
+
+        // searches outwards from the main area through anything that isn't solid, hazards included
+        let mut came_from = vec![None; in_map.tiles.len()];
+        let mut open: VecDeque<usize> = (0..in_map.tiles.len())
+            .filter(|idx| areas[*idx] == main_area)
+            .collect();
+
+        for idx in open.iter() {
+            came_from[*idx] = Some(*idx);
+        }
+
+        let mut cut_off_idx = None;
+
+        while let Some(idx) = open.pop_front() {
+            if areas[idx].is_some() && areas[idx] != main_area {
+                cut_off_idx = Some(idx);
+                break;
+            }
+
+            for next_idx in neighbours(in_map, idx) {
+                if came_from[next_idx].is_none() && is_passable(in_map.tiles[next_idx]) {
+                    came_from[next_idx] = Some(idx);
+                    open.push_back(next_idx);
+                }
+            }
+        }
+
+        // whatever is left was sealed off by walls before the liquid got there
+        let Some(mut idx) = cut_off_idx else {
+            return changed;
+        };
+
+        while areas[idx] != main_area {
+            if !is_walkable(in_map.tiles[idx]) {
+                in_map.tiles[idx] = kind.crossing_tile();
+            }
+
+            idx = came_from[idx].unwrap_or(idx);
+        }
+
+        changed = true;
+    }
+}
+
+pub struct RiverMapGenerator {
+    pub kind: LiquidKind,
+    // how many tiles across, the outermost tile either side is the edge tile
+    pub width: usize,
+}
+
+impl RiverMapGenerator {
+    pub fn new(kind: LiquidKind, width: usize) -> BoxedMapGenerator {
+        Box::new(RiverMapGenerator {
+            kind,
+            width: width.max(1),
+        })
+    }
+
+    // rivers cut through walls as well as floor, they were there first
+    fn paint(&self, in_map: &mut GameMap, (x, y): (i32, i32)) {
+        let radius = (self.width / 2) as i32;
+
+        for dy in -radius..=radius {
+            for dx in -radius..=radius {
+                // rounds the brush off so the banks aren't all straight lines
+                if dx * dx + dy * dy > radius * radius + radius {
+                    continue;
+                }
+
+                let (paint_x, paint_y) = (x + dx, y + dy);
+
+                if !in_map.is_within_bounds(paint_x, paint_y)
+                    || is_protected(in_map, paint_x as usize, paint_y as usize)
+                {
+                    continue;
+                }
+
+                let idx = in_map.xy_idx(paint_x as usize, paint_y as usize);
+
+                if radius == 0 || dx.abs().max(dy.abs()) < radius {
+                    in_map.tiles[idx] = self.kind.core_tile();
+                } else if in_map.tiles[idx] != self.kind.core_tile() {
+                    in_map.tiles[idx] = self.kind.edge_tile();
+                }
+            }
+        }
+    }
+}
+
+impl MapGenerator for RiverMapGenerator {
+    fn generate_map(&self, in_map: &mut GameMap, rng: &mut GameRNG) -> MapGenerationResult {
+        // a drunkard that has somewhere to be, it always makes it from one side of the map to the
+        // other but meanders on the way
+        let horizontal = rng.rand_range(0..2) == 0;
+        let (length, breadth) = if horizontal {
+            (in_map.width as i32, in_map.height as i32)
+        } else {
+            (in_map.height as i32, in_map.width as i32)
+        };
+
+        let mut along = 0;
+        let mut across = rng.rand_range_incl(breadth / 4..=breadth * 3 / 4);
+
+        while along < length {
+            let position = if horizontal {
+                (along, across)
+            } else {
+                (across, along)
+            };
+
+            self.paint(in_map, position);
+
+            match rng.rand_range(0..RIVER_WANDER) {
+                0 => across -= 1,
+                1 => across += 1,
+                _ => along += 1,
+            }
+
+            across = across.clamp(1, breadth - 2);
+        }
+
+        in_map.snapshot("River: channel");
+
+        if add_crossings(in_map, self.kind) {
+            in_map.snapshot("River: crossings");
+        }
+
+        Ok(GeneratorOutput::default())
+    }
+}
+
+pub struct LakeMapGenerator {
+    pub kind: LiquidKind,
+    // out of 100, how much of the open floor ends up under the lakes
+    pub percent: i32,
+    // tiles between the points the noise is made from, bigger makes fewer and larger lakes
+    pub scale: usize,
+}
+
+impl LakeMapGenerator {
+    pub fn new(kind: LiquidKind, percent: i32, scale: usize) -> BoxedMapGenerator {
+        Box::new(LakeMapGenerator {
+            kind,
+            percent: percent.clamp(0, 100),
+            scale: scale.max(2),
+        })
+    }
+
+    // value noise, random heights on a coarse grid smoothly blended across the tiles between
+    fn noise(&self, in_map: &GameMap, rng: &mut GameRNG) -> Vec<f64> {
+        let grid_width = in_map.width / self.scale + 2;
+        let grid_height = in_map.height / self.scale + 2;
+        let grid: Vec<f64> = (0..grid_width * grid_height)
+            .map(|_| rng.rand_f64())
+            .collect();
+
+        let smooth = |t: f64| t * t * (3.0 - 2.0 * t);
+        let lerp = |a: f64, b: f64, t: f64| a + (b - a) * t;
+
+        (0..in_map.tiles.len())
+            .map(|idx| {
+                let (x, y) = (idx % in_map.width, idx / in_map.width);
+                let (grid_x, grid_y) = (x / self.scale, y / self.scale);
+                let tx = smooth((x % self.scale) as f64 / self.scale as f64);
+                let ty = smooth((y % self.scale) as f64 / self.scale as f64);
+
+                let corner = |cx: usize, cy: usize| grid[cy * grid_width + cx];
+
+                lerp(
+                    lerp(corner(grid_x, grid_y), corner(grid_x + 1, grid_y), tx),
+                    lerp(
+                        corner(grid_x, grid_y + 1),
+                        corner(grid_x + 1, grid_y + 1),
+                        tx,
+                    ),
+                    ty,
+                )
+            })
+            .collect()
+    }
+}
+
+impl MapGenerator for LakeMapGenerator {
+    fn generate_map(&self, in_map: &mut GameMap, rng: &mut GameRNG) -> MapGenerationResult {
+        let noise = self.noise(in_map, rng);
+
+        // lakes only settle on open ground, the highest points of the noise flood first
+        let mut candidates: Vec<usize> = (0..in_map.tiles.len())
+            .filter(|idx| {
+                let (x, y) = (idx % in_map.width, idx / in_map.width);
+
+                is_walkable(in_map.tiles[*idx]) && !is_protected(in_map, x, y)
+            })
+            .collect();
+
+        candidates.sort_by(|a, b| noise[*b].total_cmp(&noise[*a]));
+
+        let flooded = candidates.len() * self.percent as usize / 100;
+        let deep = flooded * LAKE_CORE_PERCENT / 100;
+
+        for (rank, idx) in candidates.into_iter().take(flooded).enumerate() {
+            in_map.tiles[idx] = if rank < deep {
+                self.kind.core_tile()
+            } else {
+                self.kind.edge_tile()
+            };
+        }
+
+        in_map.snapshot("Lake: flooded");
+
+        if add_crossings(in_map, self.kind) {
+            in_map.snapshot("Lake: crossings");
+        }
+
+        Ok(GeneratorOutput::default())
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn walled_floor(width: usize, height: usize) -> GameMap {
+        let mut map = GameMap::new(width, height);
+
+        for x in 0..width {
+            for y in 0..height {
+                if is_protected(&map, x, y) {
+                    let idx = map.xy_idx(x, y);
+                    map.tiles[idx] = GameTile::UNBREAKABLE_WALL;
+                }
+            }
+        }
+
+        map
+    }
+
+    #[test]
+    fn crossings_rejoin_a_cut_off_side() {
+        for kind in [LiquidKind::Water, LiquidKind::Lava, LiquidKind::Chasm] {
+            let mut map = walled_floor(20, 10);
+
+            for y in 1..map.height - 1 {
+                let idx = map.xy_idx(8, y);
+                map.tiles[idx] = kind.core_tile();
+            }
+
+            assert_eq!(walkable_areas(&map).1.len(), 2);
+            assert!(add_crossings(&mut map, kind));
+            assert_eq!(walkable_areas(&map).1.len(), 1, "{:?} still cut off", kind);
+        }
+    }
+
+    #[test]
+    fn flooding_leaves_everywhere_reachable() {
+        for kind in [LiquidKind::Water, LiquidKind::Lava, LiquidKind::Chasm] {
+            for seed in 0..10 {
+                let mut rng = GameRNG::seeded(seed);
+                let mut map = walled_floor(30, 20);
+
+                LakeMapGenerator::new(kind, 70, 4)
+                    .generate_map(&mut map, &mut rng)
+                    .expect("lakes can't fail");
+                RiverMapGenerator::new(kind, 3)
+                    .generate_map(&mut map, &mut rng)
+                    .expect("rivers can't fail");
+
+                assert_eq!(
+                    walkable_areas(&map).1.len(),
+                    1,
+                    "{:?} with seed {} left somewhere cut off",
+                    kind,
+                    seed
+                );
+            }
+        }
+    }
+}
@@ -0,0 +1,371 @@
+use crate::{
+    game_logic::map::{
+        builder::{BoxedMapGenerator, GeneratorOutput, MapGenerationResult, MapGenerator},
+        builders::utils::find_group,
+        game_map::{GameMap, GameTile},
+    },
+    rng::GameRNG,
+    utils::Rectangle,
+};
+
+// tries at finding a spot for each reserved room before moving on
+const ROOM_PLACEMENT_ATTEMPTS: usize = 200;
+// reserved rooms are an odd number of tiles across so they line up with the maze grid
+const MIN_ROOM_SIZE: i32 = 3;
+const MAX_ROOM_SIZE: i32 = 9;
+// once everything is joined up, this many in a hundred of the leftover connectors are opened too
+const EXTRA_CONNECTOR_CHANCE: i32 = 5;
+
+const CELL_DIRECTIONS: [(i32, i32); 4] = [(0, 2), (2, 0), (0, -2), (-2, 0)];
+
+// growing tree maze carved into solid rock. cells sit on odd coordinates with walls on the even
+// ones between them, and nothing is carved within a tile of existing floor, so on a filled map it
+// makes a labyrinth and after a room generator it fills the gaps between the rooms
+pub struct MazeMapGenerator {
+    // out of 100, how often the newest cell is grown from rather than a random one. 100 is a
+    // recursive backtracker with long winding passages, 0 is closer to prim's with lots of short
+    // branches
+    pub river_factor: i32,
+    // out of 100, the chance each dead end is knocked through into a neighbouring passage
+    pub braid_percent: i32,
+    // rooms set aside before the maze is carved
+    pub room_count: usize,
+}
+
+impl MazeMapGenerator {
+    pub fn new(river_factor: i32, braid_percent: i32, room_count: usize) -> BoxedMapGenerator {
+        Box::new(MazeMapGenerator {
+            river_factor: river_factor.clamp(0, 100),
+            braid_percent: braid_percent.clamp(0, 100),
+            room_count,
+        })
+    }
+
+    fn is_cell(in_map: &GameMap, x: i32, y: i32) -> bool {
+        x > 0
+            && y > 0
+            && x < in_map.width as i32 - 1
+            && y < in_map.height as i32 - 1
+            && x % 2 == 1
+            && y % 2 == 1
+    }
+
+    fn is_floor(in_map: &GameMap, x: i32, y: i32) -> bool {
+        in_map.is_within_bounds(x, y)
+            && !in_map.tiles[in_map.xy_idx(x as usize, y as usize)].is_blocker()
+    }
+
+    // solid rock with nothing walkable around it
+    fn is_free_cell(in_map: &GameMap, x: i32, y: i32) -> bool {
+        if !MazeMapGenerator::is_cell(in_map, x, y) {
+            return false;
+        }
+
+        for dy in -1..=1 {
+            for dx in -1..=1 {
+                if MazeMapGenerator::is_floor(in_map, x + dx, y + dy) {
+                    return false;
+                }
+            }
+        }
+
+        true
+    }
+
+    fn carve(in_map: &mut GameMap, x: i32, y: i32) {
+        let idx = in_map.xy_idx(x as usize, y as usize);
+
+        in_map.tiles[idx] = GameTile::FLOOR;
+    }
+
+    fn reserve_rooms(&self, in_map: &mut GameMap, rng: &mut GameRNG) -> Vec<Rectangle> {
+        let mut rooms = Vec::new();
+
+        for _ in 0..ROOM_PLACEMENT_ATTEMPTS {
+            if rooms.len() >= self.room_count {
+                break;
+            }
+
+            let width = rng.rand_range_incl(MIN_ROOM_SIZE / 2..=MAX_ROOM_SIZE / 2) * 2 + 1;
+            let height = rng.rand_range_incl(MIN_ROOM_SIZE / 2..=MAX_ROOM_SIZE / 2) * 2 + 1;
+
+            if width + 2 > in_map.width as i32 || height + 2 > in_map.height as i32 {
+                continue;
+            }
+
+            let x = rng.rand_range_incl(0..=(in_map.width as i32 - width - 2) / 2) * 2 + 1;
+            let y = rng.rand_range_incl(0..=(in_map.height as i32 - height - 2) / 2) * 2 + 1;
+
+            // every cell the room covers has to be free, which also keeps it off other rooms
+            let fits = (y..y + height).step_by(2).all(|cy| {
+                (x..x + width)
+                    .step_by(2)
+                    .all(|cx| MazeMapGenerator::is_free_cell(in_map, cx, cy))
+            });
+
+            if !fits {
+                continue;
+            }
+
+            for ry in y..y + height {
+                for rx in x..x + width {
+                    MazeMapGenerator::carve(in_map, rx, ry);
+                }
+            }
+
+            rooms.push(Rectangle {
+                x: x as usize,
+                y: y as usize,
+                width: width as usize,
+                height: height as usize,
+            });
+            in_map.snapshot(&format!("Maze: room {}", rooms.len()));
+        }
+
+        rooms
+    }
+
+    fn grow_maze(
+        &self,
+        in_map: &mut GameMap,
+        start: (i32, i32),
+        rng: &mut GameRNG,
+    ) -> Vec<(usize, usize)> {
+        let mut passage = vec![(start.0 as usize, start.1 as usize)];
+        let mut active = vec![start];
+
+        MazeMapGenerator::carve(in_map, start.0, start.1);
+        in_map.snapshot(&format!("Maze: carve {}, {}", start.0, start.1));
+
+        while !active.is_empty() {
+            let active_idx = if rng.rand_range(0..100) < self.river_factor {
+                active.len() - 1
+            } else {
+                rng.rand_range(0..active.len() as i32) as usize
+            };
+
+            let (x, y) = active[active_idx];
+
+            let open_directions: Vec<(i32, i32)> = CELL_DIRECTIONS
+                .iter()
+                .filter(|(dx, dy)| MazeMapGenerator::is_free_cell(in_map, x + dx, y + dy))
+                .cloned()
+                .collect();
+
+            if open_directions.is_empty() {
+                active.remove(active_idx);
+                continue;
+            }
+
+            let (dx, dy) =
+                open_directions[rng.rand_range(0..open_directions.len() as i32) as usize];
+
+            // the wall between goes first, otherwise the new cell no longer counts as free
+            for (carve_x, carve_y) in [(x + dx / 2, y + dy / 2), (x + dx, y + dy)] {
+                MazeMapGenerator::carve(in_map, carve_x, carve_y);
+                passage.push((carve_x as usize, carve_y as usize));
+            }
+
+            in_map.snapshot(&format!("Maze: carve {}, {}", x + dx, y + dy));
+
+            active.push((x + dx, y + dy));
+        }
+
+        passage
+    }
+
+    // joins every separate area up through walls that have floor either side, like a spanning
+    // tree over the areas, and occasionally opens a spare one so there's more than one way round.
+    // returns the tiles it opened
+    fn connect_regions(in_map: &mut GameMap, rng: &mut GameRNG) -> Vec<(usize, usize)> {
+        in_map.calculate_regions();
+
+        let mut connectors = Vec::new();
+
+        for y in 1..in_map.height as i32 - 1 {
+            for x in 1..in_map.width as i32 - 1 {
+                if MazeMapGenerator::is_floor(in_map, x, y) {
+                    continue;
+                }
+
+                for ((ax, ay), (bx, by)) in [((x - 1, y), (x + 1, y)), ((x, y - 1), (x, y + 1))] {
+                    if let (Some(a), Some(b)) = (in_map.region_at(ax, ay), in_map.region_at(bx, by))
+                    {
+                        if a != b {
+                            connectors.push(((x, y), a, b));
+                        }
+                    }
+                }
+            }
+        }
+
+        for i in (1..connectors.len()).rev() {
+            let j = rng.rand_range_incl(0..=i as i32) as usize;
+            connectors.swap(i, j);
+        }
+
+        let mut groups: Vec<usize> = (0..in_map.region_count).collect();
+        let mut opened = Vec::new();
+
+        for ((x, y), a, b) in connectors {
+            let (group_a, group_b) = (find_group(&mut groups, a), find_group(&mut groups, b));
+
+            if group_a != group_b {
+                groups[group_a] = group_b;
+            } else if rng.rand_range(0..100) >= EXTRA_CONNECTOR_CHANCE {
+                continue;
+            }
+
+            MazeMapGenerator::carve(in_map, x, y);
+            opened.push((x as usize, y as usize));
+            in_map.snapshot(&format!("Maze: connector {}, {}", x, y));
+        }
+
+        opened
+    }
+
+    fn is_dead_end(in_map: &GameMap, x: i32, y: i32) -> bool {
+        MazeMapGenerator::is_floor(in_map, x, y)
+            && [(0, 1), (1, 0), (0, -1), (-1, 0)]
+                .iter()
+                .filter(|(dx, dy)| MazeMapGenerator::is_floor(in_map, x + dx, y + dy))
+                .count()
+                == 1
+    }
+
+    // returns the walls it knocked through
+    fn braid(
+        &self,
+        in_map: &mut GameMap,
+        passages: &[Vec<(usize, usize)>],
+        rng: &mut GameRNG,
+    ) -> Vec<(usize, usize)> {
+        let mut opened = Vec::new();
+
+        for (x, y) in passages.iter().flatten() {
+            let (x, y) = (*x as i32, *y as i32);
+
+            if !MazeMapGenerator::is_cell(in_map, x, y)
+                || !MazeMapGenerator::is_dead_end(in_map, x, y)
+                || rng.rand_range(0..100) >= self.braid_percent
+            {
+                continue;
+            }
+
+            // knocking through to another dead end gets rid of two at once
+            let mut options: Vec<(i32, i32)> = CELL_DIRECTIONS
+                .iter()
+                .filter(|(dx, dy)| {
+                    MazeMapGenerator::is_cell(in_map, x + dx, y + dy)
+                        && MazeMapGenerator::is_floor(in_map, x + dx, y + dy)
+                        && !MazeMapGenerator::is_floor(in_map, x + dx / 2, y + dy / 2)
+                })
+                .cloned()
+                .collect();
+
+            let dead_end_options: Vec<(i32, i32)> = options
+                .iter()
+                .filter(|(dx, dy)| MazeMapGenerator::is_dead_end(in_map, x + dx, y + dy))
+                .cloned()
+                .collect();
+
+            if !dead_end_options.is_empty() {
+                options = dead_end_options;
+            }
+
+            if options.is_empty() {
+                continue;
+            }
+
+            let (dx, dy) = options[rng.rand_range(0..options.len() as i32) as usize];
+
+            MazeMapGenerator::carve(in_map, x + dx / 2, y + dy / 2);
+            opened.push(((x + dx / 2) as usize, (y + dy / 2) as usize));
+            in_map.snapshot(&format!("Maze: braid {}, {}", x, y));
+        }
+
+        opened
+    }
+
+    // pockets of maze that couldn't be joined to the main part of the level, usually because the
+    // rock around them is too thick to break through with one tile, are filled back in
+    fn fill_unreachable(
+        in_map: &mut GameMap,
+        original_tiles: &[GameTile],
+        rooms: &mut Vec<Rectangle>,
+    ) {
+        in_map.calculate_regions();
+
+        let mut region_sizes = vec![0; in_map.region_count];
+
+        for region in in_map.regions.iter().flatten() {
+            region_sizes[*region] += 1;
+        }
+
+        let main_region = match (0..region_sizes.len()).max_by_key(|region| region_sizes[*region]) {
+            Some(main_region) => main_region,
+            None => return,
+        };
+
+        let mut filled = false;
+
+        for (idx, original_tile) in original_tiles.iter().enumerate() {
+            if original_tile.is_blocker()
+                && !in_map.tiles[idx].is_blocker()
+                && in_map.regions[idx] != Some(main_region)
+            {
+                in_map.tiles[idx] = *original_tile;
+                filled = true;
+            }
+        }
+
+        if filled {
+            let tiles = &in_map.tiles;
+            let width = in_map.width;
+
+            rooms.retain(|room| !tiles[room.y * width + room.x].is_blocker());
+
+            in_map.snapshot("Maze: fill unreachable");
+        }
+    }
+}
+
+impl MapGenerator for MazeMapGenerator {
+    fn generate_map(&self, in_map: &mut GameMap, rng: &mut GameRNG) -> MapGenerationResult {
+        let original_tiles = in_map.tiles.clone();
+
+        let mut rooms = self.reserve_rooms(in_map, rng);
+
+        let mut passages = Vec::new();
+
+        for y in (1..in_map.height as i32 - 1).step_by(2) {
+            for x in (1..in_map.width as i32 - 1).step_by(2) {
+                if MazeMapGenerator::is_free_cell(in_map, x, y) {
+                    passages.push(self.grow_maze(in_map, (x, y), rng));
+                }
+            }
+        }
+
+        let mut openings = MazeMapGenerator::connect_regions(in_map, rng);
+
+        openings.extend(self.braid(in_map, &passages, rng));
+        passages.push(openings);
+
+        MazeMapGenerator::fill_unreachable(in_map, &original_tiles, &mut rooms);
+
+        for passage in passages.iter_mut() {
+            passage.retain(|(x, y)| !in_map.tiles[in_map.xy_idx(*x, *y)].is_blocker());
+        }
+
+        passages.retain(|passage| !passage.is_empty());
+
+        // each separate stretch of maze is recorded as a corridor, and so is everything knocked
+        // through to join it up, which is where it meets the rooms and where the doors go
+        in_map.corridors.append(&mut passages);
+
+        Ok(GeneratorOutput {
+            rooms,
+            ..Default::default()
+        })
+    }
+}
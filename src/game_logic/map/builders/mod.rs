@@ -1,4 +1,13 @@
 pub mod bsp;
+pub mod dla;
+pub mod doors;
 pub mod drunkards;
+pub mod liquids;
+pub mod maze;
+pub mod prefab;
 pub mod spawns;
+pub mod stairs;
+pub mod town;
 pub mod utils;
+pub mod voronoi;
+pub mod wfc;
@@ -0,0 +1,246 @@
+use serde::Deserialize;
+
+use crate::{
+    game_logic::{
+        components::Position,
+        map::{
+            builder::{
+                BoxedMapGenerator, GeneratorOutput, MapGenerationError, MapGenerationResult,
+                MapGenerator,
+            },
+            game_map::{GameMap, GameTile},
+            population::SpawnEntry,
+            prefabs::{prefab_library, Prefab, PrefabCell},
+        },
+    },
+    rng::GameRNG,
+    utils::Rectangle,
+};
+
+// how many random spots are tried for each prefab before giving up on it
+const PLACEMENT_ATTEMPTS: usize = 50;
+
+#[derive(Clone, Debug, Deserialize)]
+pub enum PrefabMode {
+    // up to count prefabs dropped onto open floor anywhere in the map
+    FreeSpace { count: usize },
+    // up to count prefabs dropped inside rooms an earlier generator recorded
+    InRooms { count: usize },
+    // the named prefab is the entire level, centred with solid rock around it
+    WholeLevel { name: String },
+}
+
+pub struct PrefabMapGenerator {
+    mode: PrefabMode,
+}
+
+impl PrefabMapGenerator {
+    pub fn new(mode: PrefabMode) -> BoxedMapGenerator {
+        Box::new(PrefabMapGenerator { mode })
+    }
+
+    fn random_prefab(rng: &mut GameRNG) -> Option<Prefab> {
+        let prefabs = prefab_library().room_prefabs();
+
+        if prefabs.is_empty() {
+            return None;
+        }
+
+        let prefab = prefabs[rng.rand_range(0..prefabs.len() as i32) as usize];
+
+        let quarter_turns = if prefab.can_rotate {
+            rng.rand_range(0..4) as usize
+        } else {
+            0
+        };
+        let mirror = prefab.can_mirror && rng.rand_range(0..2) == 1;
+
+        Some(prefab.transformed(quarter_turns, mirror))
+    }
+
+    // only lands on plain floor, so it can't cover stairs, doors or another prefab
+    fn fits_free_space(in_map: &GameMap, prefab: &Prefab, x: usize, y: usize) -> bool {
+        for prefab_y in 0..prefab.height {
+            for prefab_x in 0..prefab.width {
+                let (map_x, map_y) = (x + prefab_x, y + prefab_y);
+
+                if !in_map.is_within_bounds(map_x as i32, map_y as i32)
+                    || in_map.vault_at(map_x, map_y).is_some()
+                {
+                    return false;
+                }
+
+                if *prefab.cell(prefab_x, prefab_y) != PrefabCell::Empty
+                    && in_map.tiles[in_map.xy_idx(map_x, map_y)] != GameTile::FLOOR
+                {
+                    return false;
+                }
+            }
+        }
+
+        true
+    }
+
+    fn stamp(
+        in_map: &mut GameMap,
+        prefab: &Prefab,
+        x: usize,
+        y: usize,
+        output: &mut GeneratorOutput,
+    ) {
+        for prefab_y in 0..prefab.height {
+            for prefab_x in 0..prefab.width {
+                let (map_x, map_y) = (x + prefab_x, y + prefab_y);
+
+                if !in_map.is_within_bounds(map_x as i32, map_y as i32) {
+                    continue;
+                }
+
+                let idx = in_map.xy_idx(map_x, map_y);
+
+                match prefab.cell(prefab_x, prefab_y) {
+                    PrefabCell::Empty => {}
+                    PrefabCell::Tile(tile) => in_map.tiles[idx] = *tile,
+                    PrefabCell::Spawn(name) => {
+                        in_map.tiles[idx] = GameTile::FLOOR;
+                        output.spawns.push(SpawnEntry {
+                            name: name.clone(),
+                            position: Position {
+                                x: map_x as i32,
+                                y: map_y as i32,
+                            },
+                        });
+                    }
+                    PrefabCell::PlayerSpawn => {
+                        in_map.tiles[idx] = GameTile::FLOOR;
+
+                        // a room prefab is only ever somewhere along the way, the player starts
+                        // wherever the level's own spawn step says
+                        if prefab.whole_level {
+                            output.player_spawn = Some(Position {
+                                x: map_x as i32,
+                                y: map_y as i32,
+                            });
+                        }
+                    }
+                }
+            }
+        }
+
+        in_map.vaults.push(Rectangle {
+            x,
+            y,
+            width: prefab.width,
+            height: prefab.height,
+        });
+
+        in_map.snapshot(&format!("Prefab: {}", prefab.name));
+    }
+
+    fn place_in_free_space(
+        in_map: &mut GameMap,
+        count: usize,
+        rng: &mut GameRNG,
+        output: &mut GeneratorOutput,
+    ) {
+        for _ in 0..count {
+            let prefab = match PrefabMapGenerator::random_prefab(rng) {
+                Some(prefab) => prefab,
+                None => return,
+            };
+
+            if prefab.width + 2 > in_map.width || prefab.height + 2 > in_map.height {
+                continue;
+            }
+
+            for _ in 0..PLACEMENT_ATTEMPTS {
+                let x = rng.rand_range(1..(in_map.width - prefab.width) as i32) as usize;
+                let y = rng.rand_range(1..(in_map.height - prefab.height) as i32) as usize;
+
+                if PrefabMapGenerator::fits_free_space(in_map, &prefab, x, y) {
+                    PrefabMapGenerator::stamp(in_map, &prefab, x, y, output);
+                    break;
+                }
+            }
+        }
+    }
+
+    fn place_in_rooms(
+        in_map: &mut GameMap,
+        count: usize,
+        rng: &mut GameRNG,
+        output: &mut GeneratorOutput,
+    ) {
+        for _ in 0..count {
+            let prefab = match PrefabMapGenerator::random_prefab(rng) {
+                Some(prefab) => prefab,
+                None => return,
+            };
+
+            // one prefab per room, and it has to fit inside with a tile spare all the way round so
+            // it can't cut off a corridor that comes in at the room's edge
+            let candidate_rooms: Vec<Rectangle> = in_map
+                .rooms
+                .iter()
+                .filter(|room| room.width >= prefab.width + 2 && room.height >= prefab.height + 2)
+                .filter(|room| !in_map.vaults.iter().any(|vault| vault.intersects(room)))
+                .cloned()
+                .collect();
+
+            if candidate_rooms.is_empty() {
+                continue;
+            }
+
+            let room = &candidate_rooms[rng.rand_range(0..candidate_rooms.len() as i32) as usize];
+
+            let x = room.x
+                + 1
+                + rng.rand_range_incl(0..=(room.width - prefab.width - 2) as i32) as usize;
+            let y = room.y
+                + 1
+                + rng.rand_range_incl(0..=(room.height - prefab.height - 2) as i32) as usize;
+
+            PrefabMapGenerator::stamp(in_map, &prefab, x, y, output);
+        }
+    }
+
+    fn whole_level_offset(in_map: &GameMap, prefab: &Prefab) -> (usize, usize) {
+        (
+            in_map.width.saturating_sub(prefab.width) / 2,
+            in_map.height.saturating_sub(prefab.height) / 2,
+        )
+    }
+}
+
+impl MapGenerator for PrefabMapGenerator {
+    fn generate_map(&self, in_map: &mut GameMap, rng: &mut GameRNG) -> MapGenerationResult {
+        let mut output = GeneratorOutput::default();
+
+        match &self.mode {
+            PrefabMode::FreeSpace { count } => {
+                PrefabMapGenerator::place_in_free_space(in_map, *count, rng, &mut output)
+            }
+            PrefabMode::InRooms { count } => {
+                PrefabMapGenerator::place_in_rooms(in_map, *count, rng, &mut output)
+            }
+            PrefabMode::WholeLevel { name } => {
+                let prefab = prefab_library()
+                    .get(name)
+                    .ok_or_else(|| MapGenerationError::UnknownPrefab(name.clone()))?;
+
+                in_map.fill(GameTile::UNBREAKABLE_WALL);
+                in_map.rooms.clear();
+                in_map.corridors.clear();
+                in_map.vaults.clear();
+                in_map.spawn_markers.clear();
+                in_map.biomes = vec![None; in_map.width * in_map.height];
+
+                let (x, y) = PrefabMapGenerator::whole_level_offset(in_map, prefab);
+
+                PrefabMapGenerator::stamp(in_map, prefab, x, y, &mut output);
+            }
+        }
+
+        Ok(output)
+    }
+}
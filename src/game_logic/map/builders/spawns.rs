@@ -2,7 +2,10 @@ use crate::{
     game_logic::{
         components::Position,
         map::{
-            builder::{BoxedMapGenerator, MapGenerator},
+            builder::{
+                BoxedMapGenerator, GeneratorOutput, MapGenerationError, MapGenerationResult,
+                MapGenerator,
+            },
             game_map::{GameMap, GameTile},
         },
     },
@@ -18,17 +21,26 @@ impl RandomFreeSpaceSpawn {
 }
 
 impl MapGenerator for RandomFreeSpaceSpawn {
-    fn generate_map(&self, mut in_map: GameMap, rng: &mut GameRNG) -> GameMap {
-        in_map
-    }
+    fn generate_map(&self, in_map: &mut GameMap, rng: &mut GameRNG) -> MapGenerationResult {
+        // starting shut inside a vault would skip the whole point of it
+        let floors: Vec<(usize, usize)> = in_map
+            .get_tile_pos_by_type(GameTile::FLOOR)
+            .into_iter()
+            .filter(|(x, y)| in_map.vault_at(*x, *y).is_none())
+            .collect();
+
+        if floors.is_empty() {
+            return Err(MapGenerationError::NoFreeSpace);
+        }
 
-    fn get_player_spawn(&self, mut in_map: GameMap, rng: &mut GameRNG) -> Option<Position> {
-        let floors = in_map.get_tile_pos_by_type(GameTile::Floor);
-        let mut rand_floor_pos = floors[rng.rand_range(0..floors.len() as i32) as usize];
+        let rand_floor_pos = floors[rng.rand_range(0..floors.len() as i32) as usize];
 
-        Some(Position {
-            x: rand_floor_pos.0 as i32,
-            y: rand_floor_pos.1 as i32,
+        Ok(GeneratorOutput {
+            player_spawn: Some(Position {
+                x: rand_floor_pos.0 as i32,
+                y: rand_floor_pos.1 as i32,
+            }),
+            ..Default::default()
         })
     }
 }
@@ -0,0 +1,76 @@
+use std::collections::VecDeque;
+
+use crate::{
+    game_logic::map::{
+        builder::{
+            BoxedMapGenerator, GeneratorOutput, MapGenerationError, MapGenerationResult,
+            MapGenerator,
+        },
+        game_map::{GameMap, GameTile},
+        pathfinding::is_walkable,
+    },
+    rng::GameRNG,
+};
+
+const DIRECTIONS: [(i32, i32); 4] = [(0, 1), (1, 0), (0, -1), (-1, 0)];
+
+// puts the way down as far from the player as they can walk, so the whole level is in between.
+// has to come after whatever places the player
+pub struct DownStairsGenerator {}
+
+impl DownStairsGenerator {
+    pub fn new() -> BoxedMapGenerator {
+        Box::new(DownStairsGenerator {})
+    }
+}
+
+impl MapGenerator for DownStairsGenerator {
+    fn generate_map(&self, in_map: &mut GameMap, _rng: &mut GameRNG) -> MapGenerationResult {
+        let spawn = in_map
+            .player_spawn
+            .clone()
+            .ok_or(MapGenerationError::NoPlayerSpawn)?;
+        let spawn_idx = in_map.xy_idx_pos(&spawn);
+
+        // breadth first, so the last floor tile reached is the farthest one
+        let mut visited = vec![false; in_map.tiles.len()];
+        let mut open = VecDeque::from([spawn_idx]);
+        let mut farthest = None;
+
+        visited[spawn_idx] = true;
+
+        while let Some(idx) = open.pop_front() {
+            let (x, y) = (idx % in_map.width, idx / in_map.width);
+
+            // stairs on plain floor only, never in a vault or on top of the player
+            if idx != spawn_idx
+                && in_map.tiles[idx] == GameTile::FLOOR
+                && in_map.vault_at(x, y).is_none()
+            {
+                farthest = Some((x, y));
+            }
+
+            for (dx, dy) in DIRECTIONS.iter() {
+                let (nx, ny) = (x as i32 + dx, y as i32 + dy);
+
+                if !in_map.is_within_bounds(nx, ny) {
+                    continue;
+                }
+
+                let next_idx = in_map.xy_idx(nx as usize, ny as usize);
+
+                if !visited[next_idx] && is_walkable(in_map.tiles[next_idx]) {
+                    visited[next_idx] = true;
+                    open.push_back(next_idx);
+                }
+            }
+        }
+
+        let down_stairs = farthest.ok_or(MapGenerationError::NoFreeSpace)?;
+
+        Ok(GeneratorOutput {
+            down_stairs: vec![down_stairs],
+            ..Default::default()
+        })
+    }
+}
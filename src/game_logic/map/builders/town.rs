@@ -0,0 +1,254 @@
+use crate::{
+    game_logic::{
+        components::Position,
+        map::{
+            builder::{
+                BoxedMapGenerator, GeneratorOutput, MapGenerationError, MapGenerationResult,
+                MapGenerator,
+            },
+            game_map::{GameMap, GameTile},
+        },
+    },
+    rng::GameRNG,
+    utils::Rectangle,
+};
+
+// open country between the town wall and the edge of the map, the river runs through the north
+// side
+const TOWN_MARGIN: usize = 10;
+const PLAZA_WIDTH: usize = 15;
+const PLAZA_HEIGHT: usize = 11;
+// roads are three tiles wide, one either side of the centre line
+const ROAD_HALF_WIDTH: usize = 1;
+const MIN_BUILDING_SIZE: i32 = 5;
+const MAX_BUILDING_WIDTH: i32 = 11;
+const MAX_BUILDING_HEIGHT: i32 = 9;
+// one in this many columns the river shifts up or down a tile
+const RIVER_DRIFT_CHANCE: i32 = 3;
+// random lots tried for each building before giving up on the rest
+const LOT_ATTEMPTS: usize = 100;
+
+pub struct TownMapGenerator {
+    // fewer go in if the town runs out of room
+    pub building_count: usize,
+}
+
+impl TownMapGenerator {
+    pub fn new(building_count: usize) -> BoxedMapGenerator {
+        Box::new(TownMapGenerator { building_count })
+    }
+
+    fn paint(in_map: &mut GameMap, rect: &Rectangle, tile: GameTile) {
+        for (x, y) in rect.all_squares() {
+            if in_map.is_within_bounds(x as i32, y as i32) {
+                let idx = in_map.xy_idx(x, y);
+                in_map.tiles[idx] = tile;
+            }
+        }
+    }
+
+    fn outline(in_map: &mut GameMap, rect: &Rectangle, tile: GameTile) {
+        for (x, y) in rect.all_squares() {
+            if x == rect.x
+                || y == rect.y
+                || x == rect.x + rect.width - 1
+                || y == rect.y + rect.height - 1
+            {
+                let idx = in_map.xy_idx(x, y);
+                in_map.tiles[idx] = tile;
+            }
+        }
+    }
+
+    // wanders from west to east through the margin above the town wall
+    fn dig_river(in_map: &mut GameMap, rng: &mut GameRNG) {
+        let min_y = (in_map.height - TOWN_MARGIN + 2) as i32;
+        let max_y = in_map.height as i32 - 3;
+        let mut y = rng.rand_range_incl(min_y..=max_y);
+
+        for x in 0..in_map.width {
+            // drifting every column makes it look frayed rather than winding
+            if rng.rand_range(0..RIVER_DRIFT_CHANCE) == 0 {
+                y = (y + rng.rand_range_incl(-1..=1)).clamp(min_y, max_y);
+            }
+
+            for river_y in y - 1..=y + 1 {
+                let idx = in_map.xy_idx(x, river_y as usize);
+                in_map.tiles[idx] = GameTile::DEEP_WATER;
+            }
+        }
+
+        in_map.snapshot("Town: river");
+    }
+
+    fn lay_roads(in_map: &mut GameMap, (centre_x, centre_y): (usize, usize)) {
+        // east to west right across the map, through a gate either side
+        TownMapGenerator::paint(
+            in_map,
+            &Rectangle {
+                x: 0,
+                y: centre_y - ROAD_HALF_WIDTH,
+                width: in_map.width,
+                height: ROAD_HALF_WIDTH * 2 + 1,
+            },
+            GameTile::ROAD,
+        );
+
+        // bottom to top through the north and south gates, bridging the river on the way so the
+        // bank beyond it isn't cut off
+        TownMapGenerator::paint(
+            in_map,
+            &Rectangle {
+                x: centre_x - ROAD_HALF_WIDTH,
+                y: 0,
+                width: ROAD_HALF_WIDTH * 2 + 1,
+                height: in_map.height,
+            },
+            GameTile::ROAD,
+        );
+
+        in_map.snapshot("Town: roads");
+
+        TownMapGenerator::paint(
+            in_map,
+            &Rectangle {
+                x: centre_x - PLAZA_WIDTH / 2,
+                y: centre_y - PLAZA_HEIGHT / 2,
+                width: PLAZA_WIDTH,
+                height: PLAZA_HEIGHT,
+            },
+            GameTile::ROAD,
+        );
+
+        in_map.snapshot("Town: plaza");
+    }
+
+    // the lot has to be grass with a tile of grass all round it, so no building touches another,
+    // the wall or a road and there's always a way round them all
+    fn lot_is_free(in_map: &GameMap, lot: &Rectangle) -> bool {
+        let padded = Rectangle {
+            x: lot.x - 1,
+            y: lot.y - 1,
+            width: lot.width + 2,
+            height: lot.height + 2,
+        };
+
+        padded
+            .all_squares()
+            .into_iter()
+            .all(|(x, y)| in_map.tiles[in_map.xy_idx(x, y)] == GameTile::GRASS)
+    }
+
+    fn place_buildings(
+        &self,
+        in_map: &mut GameMap,
+        town: &Rectangle,
+        centre_y: usize,
+        rng: &mut GameRNG,
+    ) -> Vec<Rectangle> {
+        let mut rooms = Vec::new();
+
+        for _ in 0..self.building_count {
+            for _ in 0..LOT_ATTEMPTS {
+                let width = rng.rand_range_incl(MIN_BUILDING_SIZE..=MAX_BUILDING_WIDTH) as usize;
+                let height = rng.rand_range_incl(MIN_BUILDING_SIZE..=MAX_BUILDING_HEIGHT) as usize;
+
+                let lot = Rectangle {
+                    x: rng.rand_range_incl(
+                        town.x as i32 + 2..=(town.x + town.width - width) as i32 - 2,
+                    ) as usize,
+                    y: rng.rand_range_incl(
+                        town.y as i32 + 2..=(town.y + town.height - height) as i32 - 2,
+                    ) as usize,
+                    width,
+                    height,
+                };
+
+                if !TownMapGenerator::lot_is_free(in_map, &lot) {
+                    continue;
+                }
+
+                TownMapGenerator::outline(in_map, &lot, GameTile::WALL);
+
+                let inside = Rectangle {
+                    x: lot.x + 1,
+                    y: lot.y + 1,
+                    width: lot.width - 2,
+                    height: lot.height - 2,
+                };
+
+                TownMapGenerator::paint(in_map, &inside, GameTile::FLOOR);
+
+                // the door looks out onto the main street
+                let door_y = if lot.y > centre_y {
+                    lot.y
+                } else {
+                    lot.y + lot.height - 1
+                };
+                let door_idx = in_map.xy_idx(lot.x + lot.width / 2, door_y);
+
+                in_map.tiles[door_idx] = GameTile::DOOR_CLOSED;
+
+                rooms.push(inside);
+
+                in_map.snapshot(&format!("Town: building {}", rooms.len()));
+
+                break;
+            }
+        }
+
+        rooms
+    }
+}
+
+impl MapGenerator for TownMapGenerator {
+    fn generate_map(&self, in_map: &mut GameMap, rng: &mut GameRNG) -> MapGenerationResult {
+        if in_map.width < TOWN_MARGIN * 2 + PLAZA_WIDTH * 2
+            || in_map.height < TOWN_MARGIN * 2 + PLAZA_HEIGHT * 2
+        {
+            return Err(MapGenerationError::MapTooSmall {
+                width: in_map.width,
+                height: in_map.height,
+            });
+        }
+
+        in_map.fill(GameTile::GRASS);
+        in_map.rooms.clear();
+        in_map.corridors.clear();
+        in_map.vaults.clear();
+        in_map.spawn_markers.clear();
+        in_map.biomes = vec![None; in_map.width * in_map.height];
+
+        in_map.snapshot("Town: grass");
+
+        TownMapGenerator::dig_river(in_map, rng);
+
+        let town = Rectangle {
+            x: TOWN_MARGIN,
+            y: TOWN_MARGIN,
+            width: in_map.width - TOWN_MARGIN * 2,
+            height: in_map.height - TOWN_MARGIN * 2,
+        };
+
+        TownMapGenerator::outline(in_map, &town, GameTile::WALL);
+
+        in_map.snapshot("Town: wall");
+
+        let (centre_x, centre_y) = town.center();
+
+        TownMapGenerator::lay_roads(in_map, (centre_x, centre_y));
+
+        let rooms = self.place_buildings(in_map, &town, centre_y, rng);
+
+        Ok(GeneratorOutput {
+            // just inside the south gate, looking up the road to the plaza
+            player_spawn: Some(Position {
+                x: centre_x as i32,
+                y: town.y as i32 + 2,
+            }),
+            rooms,
+            down_stairs: vec![(centre_x, centre_y)],
+            ..Default::default()
+        })
+    }
+}
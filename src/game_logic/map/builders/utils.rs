@@ -1,10 +1,7 @@
 use crate::{
-    game_logic::{
-        components::Position,
-        map::{
-            builder::{BoxedMapGenerator, MapGenerator},
-            game_map::{GameMap, GameTile},
-        },
+    game_logic::map::{
+        builder::{BoxedMapGenerator, GeneratorOutput, MapGenerationResult, MapGenerator},
+        game_map::{GameMap, GameTile},
     },
     rng::GameRNG,
 };
@@ -20,7 +17,7 @@ impl FillRoomGenerator {
 }
 
 impl MapGenerator for FillRoomGenerator {
-    fn generate_map(&self, mut in_map: GameMap, rng: &mut GameRNG) -> GameMap {
+    fn generate_map(&self, in_map: &mut GameMap, rng: &mut GameRNG) -> MapGenerationResult {
         for x in 0..in_map.width {
             for y in 0..in_map.height {
                 let idx = in_map.xy_idx(x, y);
@@ -28,13 +25,17 @@ impl MapGenerator for FillRoomGenerator {
                 in_map.tiles[idx] = self.tile;
             }
         }
-        in_map.snapshot();
 
-        in_map
-    }
+        // whatever was there before has been wiped out
+        in_map.rooms.clear();
+        in_map.corridors.clear();
+        in_map.vaults.clear();
+        in_map.spawn_markers.clear();
+        in_map.biomes = vec![None; in_map.width * in_map.height];
+
+        in_map.snapshot("Fill");
 
-    fn get_player_spawn(&self, mut in_map: GameMap, rng: &mut GameRNG) -> Option<Position> {
-        None
+        Ok(GeneratorOutput::default())
     }
 }
 
@@ -50,10 +51,42 @@ impl SymmetricalMapGenerator {
             vertical_symmetry,
         })
     }
+
+    // a tile and its reflections across the same axes this generator copies across, for
+    // generators that want to keep things symmetrical as they go rather than copying at the end
+    pub fn mirrored_positions(
+        in_map: &GameMap,
+        horizontal_symmetry: bool,
+        vertical_symmetry: bool,
+        (x, y): (usize, usize),
+    ) -> Vec<(usize, usize)> {
+        let mirror_x = (2 * (in_map.width / 2)) as i32 - x as i32 - 1;
+        let mirror_y = (2 * (in_map.height / 2)) as i32 - y as i32 - 1;
+
+        let mut positions = vec![(x as i32, y as i32)];
+
+        if horizontal_symmetry {
+            positions.push((mirror_x, y as i32));
+        }
+
+        if vertical_symmetry {
+            positions.push((x as i32, mirror_y));
+        }
+
+        if horizontal_symmetry && vertical_symmetry {
+            positions.push((mirror_x, mirror_y));
+        }
+
+        positions
+            .into_iter()
+            .filter(|(x, y)| in_map.is_within_bounds(*x, *y))
+            .map(|(x, y)| (x as usize, y as usize))
+            .collect()
+    }
 }
 
 impl MapGenerator for SymmetricalMapGenerator {
-    fn generate_map(&self, mut in_map: GameMap, rng: &mut GameRNG) -> GameMap {
+    fn generate_map(&self, in_map: &mut GameMap, rng: &mut GameRNG) -> MapGenerationResult {
         if self.horizontal_symmetry {
             let initial_x = in_map.width / 2;
 
@@ -64,7 +97,7 @@ impl MapGenerator for SymmetricalMapGenerator {
                 }
             }
 
-            in_map.snapshot();
+            in_map.snapshot("Symmetry: horizontal");
         }
 
         if self.vertical_symmetry {
@@ -78,14 +111,10 @@ impl MapGenerator for SymmetricalMapGenerator {
                 }
             }
 
-            in_map.snapshot();
+            in_map.snapshot("Symmetry: vertical");
         }
 
-        in_map
-    }
-
-    fn get_player_spawn(&self, in_map: GameMap, rng: &mut GameRNG) -> Option<Position> {
-        None
+        Ok(GeneratorOutput::default())
     }
 }
 
@@ -98,25 +127,78 @@ impl ReplaceVisibleWallsWithBreakableMapGenerator {
 }
 
 impl MapGenerator for ReplaceVisibleWallsWithBreakableMapGenerator {
-    fn generate_map(&self, mut in_map: GameMap, rng: &mut GameRNG) -> GameMap {
+    fn generate_map(&self, in_map: &mut GameMap, rng: &mut GameRNG) -> MapGenerationResult {
         for x in 0..in_map.width {
             for y in 0..in_map.height {
-                if in_map.tiles[in_map.xy_idx(x, y)] == GameTile::UnbreakableWall
-                    && in_map.get_adjacent_count_by_type((x as i32, y as i32), GameTile::Floor) > 0
+                // prefabs keep their walls as drawn, otherwise a pickaxe gets straight into a vault
+                if in_map.tiles[in_map.xy_idx(x, y)] == GameTile::UNBREAKABLE_WALL
+                    && in_map.get_adjacent_count_by_type((x as i32, y as i32), GameTile::FLOOR) > 0
+                    && in_map.vault_at(x, y).is_none()
                 {
                     let idx = in_map.xy_idx(x, y);
 
-                    in_map.tiles[idx] = GameTile::Wall;
+                    in_map.tiles[idx] = GameTile::WALL;
                 }
             }
         }
 
-        in_map.snapshot();
+        in_map.snapshot("Replace visible walls");
 
-        in_map
+        Ok(GeneratorOutput::default())
     }
+}
+
+// union find lookup for the generators that join things up into a spanning tree. follows each
+// entry to the root of its group, pointing it straight at the root for next time
+pub fn find_group(groups: &mut [usize], idx: usize) -> usize {
+    let mut root = idx;
+
+    while groups[root] != root {
+        root = groups[root];
+    }
+
+    groups[idx] = root;
 
-    fn get_player_spawn(&self, mut in_map: GameMap, rng: &mut GameRNG) -> Option<Position> {
-        None
+    root
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::game_logic::map::{
+        builders::prefab::{PrefabMapGenerator, PrefabMode},
+        prefabs::{prefab_library, PrefabCell},
+    };
+
+    #[test]
+    fn vault_walls_stay_unbreakable() {
+        let mut rng = GameRNG::seeded(1);
+        let mut map = GameMap::new(30, 20);
+
+        PrefabMapGenerator::new(PrefabMode::WholeLevel {
+            name: "Treasure Vault".to_owned(),
+        })
+        .generate_map(&mut map, &mut rng)
+        .expect("the treasure vault prefab exists");
+        ReplaceVisibleWallsWithBreakableMapGenerator::new()
+            .generate_map(&mut map, &mut rng)
+            .expect("replacing walls can't fail");
+
+        let prefab = prefab_library().get("Treasure Vault").unwrap();
+        let vault = map.vaults[0].clone();
+
+        for y in 0..prefab.height {
+            for x in 0..prefab.width {
+                if *prefab.cell(x, y) == PrefabCell::Tile(GameTile::UNBREAKABLE_WALL) {
+                    assert_eq!(
+                        map.tiles[map.xy_idx(vault.x + x, vault.y + y)],
+                        GameTile::UNBREAKABLE_WALL,
+                        "vault wall at {}, {} was made breakable",
+                        x,
+                        y
+                    );
+                }
+            }
+        }
     }
 }
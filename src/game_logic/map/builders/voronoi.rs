@@ -0,0 +1,391 @@
+use std::collections::BTreeSet;
+
+use serde::Deserialize;
+
+use crate::{
+    game_logic::map::{
+        builder::{BoxedMapGenerator, GeneratorOutput, MapGenerationResult, MapGenerator},
+        builders::utils::find_group,
+        game_map::{GameMap, GameTile},
+    },
+    rng::GameRNG,
+    utils::Rectangle,
+};
+
+// of the cell adjacencies left over once everything is joined up, this many in a hundred get a
+// corridor as well so the level has some loops in it
+const EXTRA_CONNECTION_CHANCE: i32 = 20;
+
+#[derive(Clone, Copy, Debug, PartialEq, Eq, Deserialize)]
+pub enum DistanceMetric {
+    // diamond shaped cells
+    Manhattan,
+    // square cells
+    Chebyshev,
+    // the usual organic looking cells
+    Euclidean,
+}
+
+impl DistanceMetric {
+    pub fn distance(&self, (x1, y1): (usize, usize), (x2, y2): (usize, usize)) -> f32 {
+        let dx = (x1 as f32 - x2 as f32).abs();
+        let dy = (y1 as f32 - y2 as f32).abs();
+
+        match self {
+            DistanceMetric::Manhattan => dx + dy,
+            DistanceMetric::Chebyshev => dx.max(dy),
+            DistanceMetric::Euclidean => (dx * dx + dy * dy).sqrt(),
+        }
+    }
+}
+
+#[derive(Clone, Debug, Deserialize)]
+pub enum VoronoiMode {
+    // carves a room in every cell and joins neighbouring cells with corridors
+    Rooms,
+    // leaves the tiles alone and gives every walkable tile the biome of the cell it is in
+    BiomePainter { biome_count: usize },
+}
+
+pub struct VoronoiMapGenerator {
+    pub seed_count: usize,
+    pub metric: DistanceMetric,
+    pub mode: VoronoiMode,
+}
+
+impl VoronoiMapGenerator {
+    pub fn new(seed_count: usize, metric: DistanceMetric, mode: VoronoiMode) -> BoxedMapGenerator {
+        Box::new(VoronoiMapGenerator {
+            seed_count: seed_count.max(1),
+            metric,
+            mode,
+        })
+    }
+
+    fn place_seeds(&self, in_map: &GameMap, rng: &mut GameRNG) -> Vec<(usize, usize)> {
+        let mut seeds = Vec::new();
+
+        while seeds.len()
+            < self
+                .seed_count
+                .min((in_map.width - 2) * (in_map.height - 2))
+        {
+            let seed = (
+                rng.rand_range(1..in_map.width as i32 - 1) as usize,
+                rng.rand_range(1..in_map.height as i32 - 1) as usize,
+            );
+
+            if !seeds.contains(&seed) {
+                seeds.push(seed);
+            }
+        }
+
+        seeds
+    }
+
+    // the index of the closest seed for every tile, ties go to the earlier seed
+    fn assign_cells(&self, in_map: &GameMap, seeds: &[(usize, usize)]) -> Vec<usize> {
+        let mut cells = vec![0; in_map.width * in_map.height];
+
+        for y in 0..in_map.height {
+            for x in 0..in_map.width {
+                let mut closest = (0, f32::MAX);
+
+                for (seed_idx, seed) in seeds.iter().enumerate() {
+                    let distance = self.metric.distance((x, y), *seed);
+
+                    if distance < closest.1 {
+                        closest = (seed_idx, distance);
+                    }
+                }
+
+                cells[in_map.xy_idx(x, y)] = closest.0;
+            }
+        }
+
+        cells
+    }
+
+    // pairs of cells that share an edge, smallest index first
+    fn cell_adjacency(in_map: &GameMap, cells: &[usize]) -> BTreeSet<(usize, usize)> {
+        let mut adjacency = BTreeSet::new();
+
+        for y in 0..in_map.height {
+            for x in 0..in_map.width {
+                let cell = cells[in_map.xy_idx(x, y)];
+
+                for (nx, ny) in [(x + 1, y), (x, y + 1)] {
+                    if nx >= in_map.width || ny >= in_map.height {
+                        continue;
+                    }
+
+                    let other = cells[in_map.xy_idx(nx, ny)];
+
+                    if cell != other {
+                        adjacency.insert((cell.min(other), cell.max(other)));
+                    }
+                }
+            }
+        }
+
+        adjacency
+    }
+
+    // a tile is inside its cell if nothing around it belongs to another cell, which leaves a wall
+    // two tiles thick between neighbouring rooms
+    fn is_cell_interior(in_map: &GameMap, cells: &[usize], x: usize, y: usize) -> bool {
+        let cell = cells[in_map.xy_idx(x, y)];
+
+        for dy in -1..=1 {
+            for dx in -1..=1 {
+                let (nx, ny) = (x as i32 + dx, y as i32 + dy);
+
+                if nx <= 0
+                    || ny <= 0
+                    || nx >= in_map.width as i32 - 1
+                    || ny >= in_map.height as i32 - 1
+                    || cells[in_map.xy_idx(nx as usize, ny as usize)] != cell
+                {
+                    return false;
+                }
+            }
+        }
+
+        true
+    }
+
+    // the biggest rectangle that can be grown out from a point without leaving the carved part of
+    // the cell, it's what gets recorded as the room for anything that wants rectangles
+    fn grow_room(
+        in_map: &GameMap,
+        cells: &[usize],
+        start: (usize, usize),
+        carved: &[bool],
+    ) -> Option<Rectangle> {
+        if !carved[in_map.xy_idx(start.0, start.1)] {
+            return None;
+        }
+
+        let cell = cells[in_map.xy_idx(start.0, start.1)];
+        let is_room_tile = |x: usize, y: usize| {
+            let idx = in_map.xy_idx(x, y);
+
+            carved[idx] && cells[idx] == cell
+        };
+
+        let (mut min_x, mut min_y, mut max_x, mut max_y) = (start.0, start.1, start.0, start.1);
+        let mut grew = true;
+
+        while grew {
+            grew = false;
+
+            if min_x > 0 && (min_y..=max_y).all(|y| is_room_tile(min_x - 1, y)) {
+                min_x -= 1;
+                grew = true;
+            }
+
+            if max_x + 1 < in_map.width && (min_y..=max_y).all(|y| is_room_tile(max_x + 1, y)) {
+                max_x += 1;
+                grew = true;
+            }
+
+            if min_y > 0 && (min_x..=max_x).all(|x| is_room_tile(x, min_y - 1)) {
+                min_y -= 1;
+                grew = true;
+            }
+
+            if max_y + 1 < in_map.height && (min_x..=max_x).all(|x| is_room_tile(x, max_y + 1)) {
+                max_y += 1;
+                grew = true;
+            }
+        }
+
+        Some(Rectangle {
+            x: min_x,
+            y: min_y,
+            width: max_x - min_x + 1,
+            height: max_y - min_y + 1,
+        })
+    }
+
+    // every cell ends up reachable through a spanning tree of the adjacency graph, built in a
+    // random order so it isn't the same shape every time, with a few extra edges on top
+    fn pick_connections(
+        &self,
+        adjacency: &BTreeSet<(usize, usize)>,
+        rng: &mut GameRNG,
+    ) -> Vec<(usize, usize)> {
+        let mut edges: Vec<(usize, usize)> = adjacency.iter().cloned().collect();
+
+        for i in (1..edges.len()).rev() {
+            let j = rng.rand_range_incl(0..=i as i32) as usize;
+            edges.swap(i, j);
+        }
+
+        let mut groups: Vec<usize> = (0..self.seed_count).collect();
+        let mut connections = Vec::new();
+
+        for (a, b) in edges {
+            let (group_a, group_b) = (find_group(&mut groups, a), find_group(&mut groups, b));
+
+            if group_a != group_b {
+                groups[group_a] = group_b;
+                connections.push((a, b));
+            } else if rng.rand_range(0..100) < EXTRA_CONNECTION_CHANCE {
+                connections.push((a, b));
+            }
+        }
+
+        connections
+    }
+
+    fn dig_corridor(
+        in_map: &mut GameMap,
+        from: (usize, usize),
+        to: (usize, usize),
+        rng: &mut GameRNG,
+    ) -> Vec<(usize, usize)> {
+        let mut corridor = vec![from];
+        let (mut x, mut y) = from;
+
+        // an l shape, which way round is random
+        let horizontal_first = rng.rand_range(0..2) == 0;
+
+        for step in 0..2 {
+            if (step == 0) == horizontal_first {
+                while x != to.0 {
+                    x = if x < to.0 { x + 1 } else { x - 1 };
+                    corridor.push((x, y));
+                }
+            } else {
+                while y != to.1 {
+                    y = if y < to.1 { y + 1 } else { y - 1 };
+                    corridor.push((x, y));
+                }
+            }
+        }
+
+        for (x, y) in corridor.iter() {
+            let idx = in_map.xy_idx(*x, *y);
+
+            if in_map.tiles[idx].is_blocker() {
+                in_map.tiles[idx] = GameTile::FLOOR;
+            }
+        }
+
+        corridor
+    }
+
+    fn carve_rooms(
+        &self,
+        in_map: &mut GameMap,
+        seeds: &[(usize, usize)],
+        cells: &[usize],
+        rng: &mut GameRNG,
+    ) -> Vec<Rectangle> {
+        let mut rooms = Vec::new();
+        let mut carved = vec![false; in_map.width * in_map.height];
+
+        for y in 0..in_map.height {
+            for x in 0..in_map.width {
+                if VoronoiMapGenerator::is_cell_interior(in_map, cells, x, y) {
+                    let idx = in_map.xy_idx(x, y);
+
+                    in_map.tiles[idx] = GameTile::FLOOR;
+                    carved[idx] = true;
+                }
+            }
+        }
+
+        in_map.snapshot("Voronoi: cells");
+
+        // corridors run between these, the seed itself if it was carved or else the closest carved
+        // tile in its cell. cells too small to have anything carved just keep their seed
+        let mut anchors = seeds.to_vec();
+
+        for (seed_idx, seed) in seeds.iter().enumerate() {
+            let mut closest = f32::MAX;
+
+            for idx in 0..carved.len() {
+                if !carved[idx] || cells[idx] != seed_idx {
+                    continue;
+                }
+
+                let tile = (idx % in_map.width, idx / in_map.width);
+                let distance = self.metric.distance(tile, *seed);
+
+                if distance < closest {
+                    closest = distance;
+                    anchors[seed_idx] = tile;
+                }
+            }
+
+            if let Some(room) =
+                VoronoiMapGenerator::grow_room(in_map, cells, anchors[seed_idx], &carved)
+            {
+                rooms.push(room);
+            }
+        }
+
+        let adjacency = VoronoiMapGenerator::cell_adjacency(in_map, cells);
+
+        for (a, b) in self.pick_connections(&adjacency, rng) {
+            let corridor = VoronoiMapGenerator::dig_corridor(in_map, anchors[a], anchors[b], rng);
+
+            // only the part outside of the rooms counts as corridor
+            in_map.corridors.push(
+                corridor
+                    .into_iter()
+                    .filter(|(x, y)| !carved[in_map.xy_idx(*x, *y)])
+                    .collect(),
+            );
+
+            in_map.snapshot(&format!("Voronoi: corridor {} to {}", a, b));
+        }
+
+        rooms
+    }
+
+    fn paint_biomes(
+        &self,
+        in_map: &mut GameMap,
+        cells: &[usize],
+        biome_count: usize,
+        rng: &mut GameRNG,
+    ) {
+        let cell_biomes: Vec<usize> = (0..self.seed_count)
+            .map(|_| rng.rand_range(0..biome_count.max(1) as i32) as usize)
+            .collect();
+
+        for idx in 0..in_map.tiles.len() {
+            in_map.biomes[idx] = if in_map.tiles[idx].is_blocker() {
+                None
+            } else {
+                Some(cell_biomes[cells[idx]])
+            };
+        }
+    }
+}
+
+impl MapGenerator for VoronoiMapGenerator {
+    fn generate_map(&self, in_map: &mut GameMap, rng: &mut GameRNG) -> MapGenerationResult {
+        if in_map.width < 3 || in_map.height < 3 {
+            return Ok(GeneratorOutput::default());
+        }
+
+        let seeds = self.place_seeds(in_map, rng);
+        let cells = self.assign_cells(in_map, &seeds);
+
+        match self.mode {
+            VoronoiMode::Rooms => Ok(GeneratorOutput {
+                rooms: self.carve_rooms(in_map, &seeds, &cells, rng),
+                ..Default::default()
+            }),
+            VoronoiMode::BiomePainter { biome_count } => {
+                self.paint_biomes(in_map, &cells, biome_count, rng);
+
+                Ok(GeneratorOutput::default())
+            }
+        }
+    }
+}
@@ -0,0 +1,563 @@
+use std::{
+    cmp::Ordering,
+    collections::{BinaryHeap, HashMap},
+};
+
+use serde::Deserialize;
+
+use bevy::prelude::*;
+
+use crate::{
+    game_logic::map::{
+        builder::{
+            BoxedMapGenerator, GeneratorOutput, MapGenerationError, MapGenerationResult,
+            MapGenerator,
+        },
+        game_map::{GameMap, GameTile},
+        prefabs::{prefab_library, PrefabCell},
+    },
+    rng::GameRNG,
+};
+
+// right, left, up, down. a direction's opposite is always its index xor 1
+const DIRECTIONS: [(i32, i32); 4] = [(1, 0), (-1, 0), (0, 1), (0, -1)];
+
+#[derive(Clone, Debug, Deserialize)]
+pub enum WfcSample {
+    // learns from whatever the earlier generators have built
+    CurrentMap,
+    // learns from the layout of a prefab file
+    Prefab(String),
+}
+
+// overlapping model wave function collapse. every pattern_size square of the sample is a pattern,
+// and the output is built so that every pattern_size square of it is one of those patterns
+pub struct WaveFunctionCollapseMapGenerator {
+    pub sample: WfcSample,
+    pub pattern_size: usize,
+    pub max_attempts: usize,
+}
+
+impl WaveFunctionCollapseMapGenerator {
+    pub fn new(sample: WfcSample, pattern_size: usize, max_attempts: usize) -> BoxedMapGenerator {
+        Box::new(WaveFunctionCollapseMapGenerator {
+            sample,
+            pattern_size: pattern_size.max(2),
+            max_attempts: max_attempts.max(1),
+        })
+    }
+
+    fn sample_tiles(
+        &self,
+        in_map: &GameMap,
+    ) -> Result<(usize, usize, Vec<GameTile>), MapGenerationError> {
+        match &self.sample {
+            WfcSample::CurrentMap => Ok((in_map.width, in_map.height, in_map.tiles.clone())),
+            WfcSample::Prefab(name) => {
+                let prefab = prefab_library()
+                    .get(name)
+                    .ok_or_else(|| MapGenerationError::UnknownPrefab(name.clone()))?;
+
+                let mut tiles = Vec::with_capacity(prefab.width * prefab.height);
+
+                for y in 0..prefab.height {
+                    for x in 0..prefab.width {
+                        tiles.push(match prefab.cell(x, y) {
+                            PrefabCell::Empty => GameTile::UNBREAKABLE_WALL,
+                            PrefabCell::Tile(tile) => *tile,
+                            PrefabCell::Spawn(_) | PrefabCell::PlayerSpawn => GameTile::FLOOR,
+                        });
+                    }
+                }
+
+                Ok((prefab.width, prefab.height, tiles))
+            }
+        }
+    }
+}
+
+struct Patterns {
+    size: usize,
+    tiles: Vec<Vec<GameTile>>,
+    weights: Vec<f64>,
+    // compatible[pattern][direction] is every pattern that can sit that way from it
+    compatible: Vec<[Vec<usize>; 4]>,
+}
+
+impl Patterns {
+    fn learn(width: usize, height: usize, sample: &[GameTile], size: usize) -> Option<Patterns> {
+        if width < size || height < size {
+            return None;
+        }
+
+        let mut pattern_ids: HashMap<Vec<GameTile>, usize> = HashMap::new();
+        let mut tiles = Vec::new();
+        let mut weights = Vec::new();
+
+        for y in 0..=(height - size) {
+            for x in 0..=(width - size) {
+                let mut pattern = Vec::with_capacity(size * size);
+
+                for py in 0..size {
+                    for px in 0..size {
+                        pattern.push(sample[(y + py) * width + x + px]);
+                    }
+                }
+
+                // a vec keeps the patterns in the order they were found, so the same sample
+                // always gives the same ids and the same seed always gives the same map
+                match pattern_ids.get(&pattern) {
+                    Some(id) => weights[*id] += 1.,
+                    None => {
+                        pattern_ids.insert(pattern.clone(), tiles.len());
+                        tiles.push(pattern);
+                        weights.push(1.);
+                    }
+                }
+            }
+        }
+
+        let mut patterns = Patterns {
+            size,
+            tiles,
+            weights,
+            compatible: Vec::new(),
+        };
+
+        for a in 0..patterns.tiles.len() {
+            let mut compatible: [Vec<usize>; 4] = Default::default();
+
+            for (direction, (dx, dy)) in DIRECTIONS.iter().enumerate() {
+                for b in 0..patterns.tiles.len() {
+                    if patterns.agrees(a, b, *dx, *dy) {
+                        compatible[direction].push(b);
+                    }
+                }
+            }
+
+            patterns.compatible.push(compatible);
+        }
+
+        Some(patterns)
+    }
+
+    // whether b placed at (dx, dy) from a matches a everywhere the two overlap
+    fn agrees(&self, a: usize, b: usize, dx: i32, dy: i32) -> bool {
+        let size = self.size as i32;
+
+        for y in dy.max(0)..(size + dy).min(size) {
+            for x in dx.max(0)..(size + dx).min(size) {
+                let a_tile = self.tiles[a][(y * size + x) as usize];
+                let b_tile = self.tiles[b][((y - dy) * size + x - dx) as usize];
+
+                if a_tile != b_tile {
+                    return false;
+                }
+            }
+        }
+
+        true
+    }
+}
+
+// an undecided cell as it was when it went on the heap. the heap pops the lowest entropy first,
+// and an entry is stale once the cell has lost another pattern since it was pushed
+struct EntropyEntry {
+    entropy: f64,
+    cell: usize,
+    possible_count: usize,
+}
+
+impl PartialEq for EntropyEntry {
+    fn eq(&self, other: &Self) -> bool {
+        self.cmp(other) == Ordering::Equal
+    }
+}
+
+impl Eq for EntropyEntry {}
+
+impl PartialOrd for EntropyEntry {
+    fn partial_cmp(&self, other: &Self) -> Option<Ordering> {
+        Some(self.cmp(other))
+    }
+}
+
+impl Ord for EntropyEntry {
+    // backwards, binary heaps are max heaps
+    fn cmp(&self, other: &Self) -> Ordering {
+        other
+            .entropy
+            .total_cmp(&self.entropy)
+            .then_with(|| other.cell.cmp(&self.cell))
+    }
+}
+
+struct Wave {
+    width: usize,
+    height: usize,
+    pattern_count: usize,
+    possible: Vec<bool>,
+    possible_count: Vec<usize>,
+    // support[direction] is how many patterns in the cell `direction` back from this one still
+    // allow it, once that hits zero the pattern is banned
+    support: Vec<[u32; 4]>,
+    weight_sums: Vec<f64>,
+    weight_log_sums: Vec<f64>,
+    collapsed: Vec<Option<usize>>,
+    // a little noise per cell to break ties between cells with the same entropy
+    noise: Vec<f64>,
+    ban_stack: Vec<(usize, usize)>,
+    entropy_heap: BinaryHeap<EntropyEntry>,
+}
+
+impl Wave {
+    fn new(width: usize, height: usize, patterns: &Patterns, rng: &mut GameRNG) -> Wave {
+        let pattern_count = patterns.tiles.len();
+        let cell_count = width * height;
+
+        let initial_support: Vec<[u32; 4]> = (0..pattern_count)
+            .map(|pattern| {
+                let mut support = [0; 4];
+
+                for (direction, count) in support.iter_mut().enumerate() {
+                    // support[direction] counts the patterns in the cell that this one is
+                    // `direction` away from, which are the ones it allows the opposite way
+                    *count = patterns.compatible[pattern][direction ^ 1].len() as u32;
+                }
+
+                support
+            })
+            .collect();
+
+        let weight_sum: f64 = patterns.weights.iter().sum();
+        let weight_log_sum: f64 = patterns.weights.iter().map(|w| w * w.ln()).sum();
+
+        let mut wave = Wave {
+            width,
+            height,
+            pattern_count,
+            possible: vec![true; cell_count * pattern_count],
+            possible_count: vec![pattern_count; cell_count],
+            support: (0..cell_count)
+                .flat_map(|_| initial_support.iter().cloned())
+                .collect(),
+            weight_sums: vec![weight_sum; cell_count],
+            weight_log_sums: vec![weight_log_sum; cell_count],
+            collapsed: vec![None; cell_count],
+            noise: (0..cell_count).map(|_| rng.rand_f64() * 1e-6).collect(),
+            ban_stack: Vec::new(),
+            entropy_heap: BinaryHeap::with_capacity(cell_count),
+        };
+
+        // a pattern that nothing can sit next to one way is ruled out anywhere it would have a
+        // neighbour that way. the caller propagates these before the first collapse
+        for cell in 0..cell_count {
+            let (x, y) = ((cell % width) as i32, (cell / width) as i32);
+
+            for pattern in 0..pattern_count {
+                let unsupported = DIRECTIONS.iter().enumerate().any(|(direction, (dx, dy))| {
+                    let (nx, ny) = (x - dx, y - dy);
+
+                    nx >= 0
+                        && ny >= 0
+                        && nx < width as i32
+                        && ny < height as i32
+                        && wave.support[cell * pattern_count + pattern][direction] == 0
+                });
+
+                if unsupported {
+                    wave.ban(cell, pattern, patterns);
+                }
+            }
+
+            wave.push_entropy(cell);
+        }
+
+        wave
+    }
+
+    fn push_entropy(&mut self, cell: usize) {
+        if self.possible_count[cell] <= 1 {
+            return;
+        }
+
+        self.entropy_heap.push(EntropyEntry {
+            entropy: self.entropy(cell) + self.noise[cell],
+            cell,
+            possible_count: self.possible_count[cell],
+        });
+    }
+
+    fn ban(&mut self, cell: usize, pattern: usize, patterns: &Patterns) {
+        let idx = cell * self.pattern_count + pattern;
+
+        if !self.possible[idx] {
+            return;
+        }
+
+        self.possible[idx] = false;
+        self.possible_count[cell] -= 1;
+
+        let weight = patterns.weights[pattern];
+        self.weight_sums[cell] -= weight;
+        self.weight_log_sums[cell] -= weight * weight.ln();
+
+        self.ban_stack.push((cell, pattern));
+        self.push_entropy(cell);
+    }
+
+    fn entropy(&self, cell: usize) -> f64 {
+        let weight_sum = self.weight_sums[cell];
+
+        weight_sum.ln() - self.weight_log_sums[cell] / weight_sum
+    }
+
+    // returns false on a contradiction
+    fn propagate(&mut self, patterns: &Patterns) -> bool {
+        while let Some((cell, pattern)) = self.ban_stack.pop() {
+            if self.possible_count[cell] == 0 {
+                return false;
+            }
+
+            let (x, y) = ((cell % self.width) as i32, (cell / self.width) as i32);
+
+            for (direction, (dx, dy)) in DIRECTIONS.iter().enumerate() {
+                let (nx, ny) = (x + dx, y + dy);
+
+                if nx < 0 || ny < 0 || nx >= self.width as i32 || ny >= self.height as i32 {
+                    continue;
+                }
+
+                let neighbour = ny as usize * self.width + nx as usize;
+
+                for other in patterns.compatible[pattern][direction].iter() {
+                    let support =
+                        &mut self.support[neighbour * self.pattern_count + other][direction];
+
+                    if *support == 0 {
+                        continue;
+                    }
+
+                    *support -= 1;
+
+                    if *support == 0 {
+                        self.ban(neighbour, *other, patterns);
+                    }
+                }
+            }
+        }
+
+        true
+    }
+
+    // the undecided cell with the least entropy
+    fn next_cell(&mut self) -> Option<usize> {
+        while let Some(entry) = self.entropy_heap.pop() {
+            let possible_count = self.possible_count[entry.cell];
+
+            if possible_count > 1 && possible_count == entry.possible_count {
+                return Some(entry.cell);
+            }
+        }
+
+        None
+    }
+
+    fn collapse(&mut self, cell: usize, patterns: &Patterns, rng: &mut GameRNG) {
+        let mut roll = rng.rand_f64() * self.weight_sums[cell];
+        let mut chosen = None;
+
+        for pattern in 0..self.pattern_count {
+            if !self.possible[cell * self.pattern_count + pattern] {
+                continue;
+            }
+
+            chosen = Some(pattern);
+            roll -= patterns.weights[pattern];
+
+            if roll <= 0. {
+                break;
+            }
+        }
+
+        for pattern in 0..self.pattern_count {
+            if Some(pattern) != chosen {
+                self.ban(cell, pattern, patterns);
+            }
+        }
+
+        self.collapsed[cell] = chosen;
+    }
+
+    fn decided_pattern(&self, cell: usize) -> Option<usize> {
+        if let Some(pattern) = self.collapsed[cell] {
+            return Some(pattern);
+        }
+
+        if self.possible_count[cell] != 1 {
+            return None;
+        }
+
+        (0..self.pattern_count).find(|pattern| self.possible[cell * self.pattern_count + pattern])
+    }
+
+    // writes every decided cell into the map, undecided ones are left as they were. the cells
+    // along the top and right edges also fill in the rest of their pattern so the whole map is
+    // covered
+    fn write_to_map(&self, in_map: &mut GameMap, patterns: &Patterns) {
+        for y in 0..self.height {
+            for x in 0..self.width {
+                let pattern = match self.decided_pattern(y * self.width + x) {
+                    Some(pattern) => pattern,
+                    None => continue,
+                };
+
+                let size_x = if x == self.width - 1 {
+                    patterns.size
+                } else {
+                    1
+                };
+                let size_y = if y == self.height - 1 {
+                    patterns.size
+                } else {
+                    1
+                };
+
+                for py in 0..size_y {
+                    for px in 0..size_x {
+                        let idx = in_map.xy_idx(x + px, y + py);
+
+                        in_map.tiles[idx] = patterns.tiles[pattern][py * patterns.size + px];
+                    }
+                }
+            }
+        }
+    }
+}
+
+impl WaveFunctionCollapseMapGenerator {
+    // the patterns only promise that things line up locally, so the collapse can leave pockets of
+    // floor nothing else connects to, and floor along the edge of the map if the sample had any.
+    // both are walled up. returns whether anything had to change
+    fn keep_largest_region(in_map: &mut GameMap) -> bool {
+        let mut filled = false;
+
+        for x in 0..in_map.width {
+            for y in 0..in_map.height {
+                let idx = in_map.xy_idx(x, y);
+
+                if (x == 0 || y == 0 || x == in_map.width - 1 || y == in_map.height - 1)
+                    && in_map.tiles[idx] != GameTile::UNBREAKABLE_WALL
+                {
+                    in_map.tiles[idx] = GameTile::UNBREAKABLE_WALL;
+                    filled = true;
+                }
+            }
+        }
+
+        in_map.calculate_regions();
+
+        let mut region_sizes = vec![0; in_map.region_count];
+
+        for region in in_map.regions.iter().flatten() {
+            region_sizes[*region] += 1;
+        }
+
+        let main_region = (0..region_sizes.len()).max_by_key(|region| region_sizes[*region]);
+
+        for idx in 0..in_map.tiles.len() {
+            if in_map.regions[idx].is_some() && in_map.regions[idx] != main_region {
+                in_map.tiles[idx] = GameTile::UNBREAKABLE_WALL;
+                filled = true;
+            }
+        }
+
+        if filled {
+            in_map.calculate_regions();
+        }
+
+        filled
+    }
+}
+
+impl MapGenerator for WaveFunctionCollapseMapGenerator {
+    fn generate_map(&self, in_map: &mut GameMap, rng: &mut GameRNG) -> MapGenerationResult {
+        let (sample_width, sample_height, sample) = self.sample_tiles(in_map)?;
+
+        let patterns = Patterns::learn(sample_width, sample_height, &sample, self.pattern_size)
+            .ok_or(MapGenerationError::WfcSampleTooSmall {
+                pattern_size: self.pattern_size,
+            })?;
+
+        if in_map.width < self.pattern_size || in_map.height < self.pattern_size {
+            return Ok(GeneratorOutput::default());
+        }
+
+        let (wave_width, wave_height) = (
+            in_map.width - self.pattern_size + 1,
+            in_map.height - self.pattern_size + 1,
+        );
+        // roughly twenty frames of the collapse end up in the history
+        let snapshot_every = (wave_width * wave_height / 20).max(1);
+
+        for attempt in 0..self.max_attempts {
+            let mut wave = Wave::new(wave_width, wave_height, &patterns, rng);
+            let mut working_map = in_map.clone();
+            let mut collapse_count = 0;
+            let mut contradiction = !wave.propagate(&patterns);
+
+            working_map.fill(GameTile::UNBREAKABLE_WALL);
+
+            while let Some(cell) = wave.next_cell().filter(|_| !contradiction) {
+                wave.collapse(cell, &patterns, rng);
+
+                if !wave.propagate(&patterns) {
+                    contradiction = true;
+                    break;
+                }
+
+                collapse_count += 1;
+
+                if collapse_count % snapshot_every == 0 {
+                    wave.write_to_map(&mut working_map, &patterns);
+                    working_map.snapshot(&format!(
+                        "WFC: attempt {}, {} collapsed",
+                        attempt + 1,
+                        collapse_count
+                    ));
+                }
+            }
+
+            if contradiction || wave.possible_count.contains(&0) {
+                info!(
+                    "wfc hit a contradiction on attempt {}, retrying",
+                    attempt + 1
+                );
+                in_map.history.append(&mut working_map.history);
+                continue;
+            }
+
+            wave.write_to_map(&mut working_map, &patterns);
+
+            // nothing from the sample's rooms makes sense in the new layout
+            working_map.rooms.clear();
+            working_map.corridors.clear();
+            working_map.vaults.clear();
+            working_map.spawn_markers.clear();
+            working_map.biomes = vec![None; working_map.width * working_map.height];
+            working_map.snapshot("WFC: done");
+
+            if Self::keep_largest_region(&mut working_map) {
+                working_map.snapshot("WFC: fill unreachable");
+            }
+
+            *in_map = working_map;
+
+            return Ok(GeneratorOutput::default());
+        }
+
+        Err(MapGenerationError::WfcContradiction {
+            attempts: self.max_attempts,
+        })
+    }
+}
@@ -0,0 +1,56 @@
+use bevy::prelude::*;
+
+use crate::game_logic::components::{Digger, Position, Viewshed};
+
+use super::game_map::GameMap;
+
+// sent whenever the terrain changes during play, anything that caches what it can see or where it
+// can walk needs to take another look around this position
+pub struct TileChangedEvent {
+    pub position: Position,
+}
+
+// one turn of work on the wall at x, y, returns true once it has been dug out. moving on to a
+// different wall loses any progress made on the last one
+pub fn dig_at(map: &mut GameMap, digger: &mut Digger, x: i32, y: i32) -> bool {
+    if !map.is_diggable(x, y) {
+        digger.target = None;
+        digger.progress = 0;
+
+        return false;
+    }
+
+    let target = Position { x, y };
+
+    if digger.target.as_ref() != Some(&target) {
+        digger.target = Some(target);
+        digger.progress = 0;
+    }
+
+    digger.progress += 1;
+
+    if digger.progress < digger.dig_turns {
+        return false;
+    }
+
+    digger.target = None;
+    digger.progress = 0;
+
+    map.dig_out(x, y)
+}
+
+pub fn handle_tile_changed_events(
+    mut tile_changed_events: EventReader<TileChangedEvent>,
+    mut viewshed_query: Query<(&Position, &mut Viewshed)>,
+) {
+    for event in tile_changed_events.iter() {
+        for (position, mut viewshed) in viewshed_query.iter_mut() {
+            let (dx, dy) = (position.x - event.position.x, position.y - event.position.y);
+            let distance = viewshed.distance as i32;
+
+            if dx * dx + dy * dy <= distance * distance {
+                viewshed.dirty = true;
+            }
+        }
+    }
+}
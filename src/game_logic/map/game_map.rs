@@ -1,60 +1,78 @@
 use bevy::prelude::Color;
 
-use crate::game_logic::components::Position;
-
-#[derive(Clone, Copy, PartialEq, PartialOrd, Eq)]
-pub enum GameTile {
-    Floor,
-    Wall,
-    UnbreakableWall,
-    DownStairs,
-    UpStairs,
-}
+use crate::{game_logic::components::Position, utils::Rectangle};
+
+use super::{
+    population::SpawnEntry,
+    tile_definitions::{tile_definitions, Terrain, TileDefinition},
+};
+
+// a compact id into the tile definitions, everything about what a tile looks like and how it
+// behaves lives in the data file rather than here
+#[derive(Clone, Copy, PartialEq, PartialOrd, Eq, Hash, Debug)]
+pub struct GameTile(pub u16);
 
 impl GameTile {
+    pub const FLOOR: GameTile = GameTile(0);
+    pub const WALL: GameTile = GameTile(1);
+    pub const UNBREAKABLE_WALL: GameTile = GameTile(2);
+    pub const DOWN_STAIRS: GameTile = GameTile(3);
+    pub const UP_STAIRS: GameTile = GameTile(4);
+    pub const DOOR_CLOSED: GameTile = GameTile(5);
+    pub const DOOR_OPEN: GameTile = GameTile(6);
+    pub const GRASS: GameTile = GameTile(7);
+    pub const ROAD: GameTile = GameTile(8);
+    pub const DEEP_WATER: GameTile = GameTile(9);
+    pub const SHALLOW_WATER: GameTile = GameTile(10);
+    pub const LAVA: GameTile = GameTile(11);
+    pub const CHASM: GameTile = GameTile(12);
+
+    pub fn by_name(name: &str) -> Option<GameTile> {
+        tile_definitions().by_name(name)
+    }
+
+    pub fn definition(&self) -> &'static TileDefinition {
+        tile_definitions().get(*self)
+    }
+
     pub fn get_char_rep(&self) -> u16 {
-        match self {
-            GameTile::Floor => '.' as u16,
-            GameTile::Wall => '#' as u16,
-            GameTile::UnbreakableWall => 178 as u16, // ▓, doesn't like this one
-            GameTile::DownStairs => 31 as u16,
-            GameTile::UpStairs => 30 as u16,
-        }
+        self.definition().glyph
     }
 
     pub fn is_blocker(&self) -> bool {
-        match self {
-            GameTile::Floor => false,
-            GameTile::Wall => true,
-            GameTile::UnbreakableWall => true,
-            GameTile::DownStairs => false,
-            GameTile::UpStairs => false,
-        }
+        self.definition().blocks_movement
     }
 
     pub fn is_opaque(&self) -> bool {
-        match self {
-            GameTile::Floor => false,
-            GameTile::Wall => true,
-            GameTile::UnbreakableWall => true,
-            GameTile::DownStairs => false,
-            GameTile::UpStairs => false,
-        }
+        self.definition().blocks_sight
+    }
+
+    pub fn is_diggable(&self) -> bool {
+        self.definition().diggable
+    }
+
+    pub fn terrain(&self) -> Terrain {
+        self.definition().terrain
+    }
+
+    pub fn is_door(&self) -> bool {
+        *self == GameTile::DOOR_CLOSED || *self == GameTile::DOOR_OPEN
     }
 
     pub fn default_tile_colour(&self) -> Color {
-        match self {
-            GameTile::Floor => Color::GRAY,
-            GameTile::Wall => Color::BEIGE,
-            GameTile::UnbreakableWall => Color::MIDNIGHT_BLUE,
-            GameTile::DownStairs => Color::RED,
-            GameTile::UpStairs => Color::GREEN,
-        }
+        self.definition().fg
     }
 }
 
 pub type GameMapTiles2D = Vec<GameTile>;
 
+// one frame of map generation for the visualiser, with a note of what the generator was doing
+#[derive(Clone)]
+pub struct MapSnapshot {
+    pub tiles: GameMapTiles2D,
+    pub label: String,
+}
+
 #[derive(Clone)]
 pub struct GameMap {
     pub width: usize,
@@ -62,12 +80,27 @@ pub struct GameMap {
     pub tiles: GameMapTiles2D,
     pub viewed_tiles: Vec<bool>,
     pub blocking_tiles: Vec<bool>,
-    pub history: Vec<GameMapTiles2D>,
+    pub history: Vec<MapSnapshot>,
+    // snapshots copy the whole map, so they're only taken when something is going to play them back
+    pub record_history: bool,
+    // structure left behind by the generators, empty if the generator has no idea of rooms
+    pub rooms: Vec<Rectangle>,
+    pub corridors: Vec<Vec<(usize, usize)>>,
+    // areas taken up by prefabs, and whatever they asked to have spawned in them
+    pub vaults: Vec<Rectangle>,
+    pub spawn_markers: Vec<SpawnEntry>,
+    // where the player starts, once a generator has picked somewhere
+    pub player_spawn: Option<Position>,
+    // theming ids painted on by a region painter, None where nothing has been painted
+    pub biomes: Vec<Option<usize>>,
+    // connected walkable areas, None for anything that blocks movement
+    pub regions: Vec<Option<usize>>,
+    pub region_count: usize,
 }
 
 impl GameMap {
     pub fn new(width: usize, height: usize) -> GameMap {
-        let new_map = vec![GameTile::Floor; width * height];
+        let new_map = vec![GameTile::FLOOR; width * height];
 
         GameMap {
             width,
@@ -76,6 +109,15 @@ impl GameMap {
             viewed_tiles: vec![false; width * height],
             blocking_tiles: vec![false; width * height],
             history: Vec::new(),
+            record_history: false,
+            rooms: Vec::new(),
+            corridors: Vec::new(),
+            vaults: Vec::new(),
+            spawn_markers: Vec::new(),
+            player_spawn: None,
+            biomes: vec![None; width * height],
+            regions: vec![None; width * height],
+            region_count: 0,
         }
     }
 
@@ -99,6 +141,72 @@ impl GameMap {
         self.blocking_tiles[self.xy_idx(x as usize, y as usize)]
     }
 
+    pub fn is_closed_door(&self, x: i32, y: i32) -> bool {
+        self.is_within_bounds(x, y)
+            && self.tiles[self.xy_idx(x as usize, y as usize)] == GameTile::DOOR_CLOSED
+    }
+
+    // off the map counts as plain ground, the bounds checks elsewhere keep anything from going there
+    pub fn terrain_at(&self, x: i32, y: i32) -> Terrain {
+        if self.is_within_bounds(x, y) {
+            self.tiles[self.xy_idx(x as usize, y as usize)].terrain()
+        } else {
+            Terrain::Ground
+        }
+    }
+
+    pub fn is_open_door(&self, x: i32, y: i32) -> bool {
+        self.is_within_bounds(x, y)
+            && self.tiles[self.xy_idx(x as usize, y as usize)] == GameTile::DOOR_OPEN
+    }
+
+    // blocking is updated straight away rather than waiting for the next blocking update, so
+    // whoever opened the door can walk through it on their next move
+    pub fn open_door(&mut self, x: i32, y: i32) -> bool {
+        if !self.is_closed_door(x, y) {
+            return false;
+        }
+
+        let idx = self.xy_idx(x as usize, y as usize);
+
+        self.tiles[idx] = GameTile::DOOR_OPEN;
+        self.blocking_tiles[idx] = false;
+
+        true
+    }
+
+    pub fn close_door(&mut self, x: i32, y: i32) -> bool {
+        if !self.is_open_door(x, y) {
+            return false;
+        }
+
+        let idx = self.xy_idx(x as usize, y as usize);
+
+        self.tiles[idx] = GameTile::DOOR_CLOSED;
+        self.blocking_tiles[idx] = true;
+
+        true
+    }
+
+    pub fn is_diggable(&self, x: i32, y: i32) -> bool {
+        self.is_within_bounds(x, y) && self.tiles[self.xy_idx(x as usize, y as usize)].is_diggable()
+    }
+
+    // anything diggable comes out as floor, the regions are redone as this can join two of them
+    pub fn dig_out(&mut self, x: i32, y: i32) -> bool {
+        if !self.is_diggable(x, y) {
+            return false;
+        }
+
+        let idx = self.xy_idx(x as usize, y as usize);
+
+        self.tiles[idx] = GameTile::FLOOR;
+        self.blocking_tiles[idx] = false;
+        self.calculate_regions();
+
+        true
+    }
+
     pub fn fill(&mut self, tile: GameTile) {
         for x in 0..self.width {
             for y in 0..self.height {
@@ -109,8 +217,15 @@ impl GameMap {
         }
     }
 
-    pub fn snapshot(&mut self) {
-        self.history.push(self.tiles.clone())
+    pub fn snapshot(&mut self, label: &str) {
+        if !self.record_history {
+            return;
+        }
+
+        self.history.push(MapSnapshot {
+            tiles: self.tiles.clone(),
+            label: label.to_owned(),
+        })
     }
 
     pub fn clear_history(&mut self) {
@@ -203,4 +318,74 @@ impl GameMap {
 
         res_count
     }
+
+    fn is_region_tile(&self, idx: usize) -> bool {
+        !self.tiles[idx].is_blocker() || self.tiles[idx].is_door()
+    }
+
+    pub fn region_at(&self, x: i32, y: i32) -> Option<usize> {
+        if !self.is_within_bounds(x, y) {
+            return None;
+        }
+
+        self.regions[self.xy_idx(x as usize, y as usize)]
+    }
+
+    pub fn biome_at(&self, x: i32, y: i32) -> Option<usize> {
+        if !self.is_within_bounds(x, y) {
+            return None;
+        }
+
+        self.biomes[self.xy_idx(x as usize, y as usize)]
+    }
+
+    pub fn vault_at(&self, x: usize, y: usize) -> Option<usize> {
+        self.vaults.iter().position(|vault| {
+            x >= vault.x && x < vault.x + vault.width && y >= vault.y && y < vault.y + vault.height
+        })
+    }
+
+    pub fn room_at(&self, x: usize, y: usize) -> Option<usize> {
+        self.rooms.iter().position(|room| {
+            x >= room.x && x < room.x + room.width && y >= room.y && y < room.y + room.height
+        })
+    }
+
+    // flood fills every walkable tile, each separate area gets its own id. doors join the areas
+    // either side of them whether they're open or not
+    pub fn calculate_regions(&mut self) {
+        let mut regions = vec![None; self.width * self.height];
+        let mut region_count = 0;
+
+        for start_idx in 0..self.tiles.len() {
+            if regions[start_idx].is_some() || !self.is_region_tile(start_idx) {
+                continue;
+            }
+
+            let mut open_list = vec![start_idx];
+            regions[start_idx] = Some(region_count);
+
+            while let Some(idx) = open_list.pop() {
+                let (x, y) = ((idx % self.width) as i32, (idx / self.width) as i32);
+
+                for (nx, ny) in [(x, y + 1), (x, y - 1), (x + 1, y), (x - 1, y)] {
+                    if !self.is_within_bounds(nx, ny) {
+                        continue;
+                    }
+
+                    let n_idx = self.xy_idx(nx as usize, ny as usize);
+
+                    if regions[n_idx].is_none() && self.is_region_tile(n_idx) {
+                        regions[n_idx] = Some(region_count);
+                        open_list.push(n_idx);
+                    }
+                }
+            }
+
+            region_count += 1;
+        }
+
+        self.regions = regions;
+        self.region_count = region_count;
+    }
 }
@@ -1,78 +1,327 @@
-use bevy::prelude::*;
+use std::sync::{
+    atomic::{AtomicUsize, Ordering},
+    Arc,
+};
+
+use bevy::{
+    prelude::*,
+    tasks::{AsyncComputeTaskPool, Task},
+};
+use futures_lite::future;
 use iyes_loopless::prelude::*;
 
 use crate::{
     game_logic::{
-        components::{Blocker, Position, Renderable},
+        components::{Player, Position, Viewshed},
         map::{
-            builder::MapBuilder,
-            builders::{
-                bsp::BSPRoomMapGenerator,
-                spawns::RandomFreeSpaceSpawn,
-                utils::{FillRoomGenerator, ReplaceVisibleWallsWithBreakableMapGenerator},
-            },
-            game_map::GameTile,
+            builder::{BuiltMap, MapGenerationError},
+            population::{populate_level, LevelSpawns},
+            recipes::map_recipes,
+            spatial_index::SpatialIndex,
         },
-        resources::PlayerResource,
+        raws::{spawner::spawn_named_entity, RawMaster},
+        resources::{DungeonLevel, PlayerResource},
     },
     rng::GameRNG,
-    screen::structs::{ScreenContext, ScreenTilePriority},
-    GameState, InGameState,
+    screen::{
+        structs::{ScreenContext, ScreenTilePriority},
+        viewport::{MapViewport, UI_PANEL_HEIGHT},
+    },
+    settings::Settings,
+    GameState, InGameState, InMenuState,
 };
 
-use super::game_map::{GameMap, GameMapTiles2D};
+use super::game_map::{GameMap, MapSnapshot};
+
+// maps are no longer tied to the screen size, the viewport scrolls around them
+pub const MAP_WIDTH: usize = 120;
+pub const MAP_HEIGHT: usize = 80;
+
+// how long each frame of the visualisation stays up, up and down halve and double it
+const DEFAULT_FRAME_TIME_MS: u128 = 50;
+const MIN_FRAME_TIME_MS: u128 = 1;
+const MAX_FRAME_TIME_MS: u128 = 1600;
+const FALLBACK_ATTEMPTS: usize = 5;
 
 pub struct MapVisualisation {
     tick_count_ms: u128,
+    frame_time_ms: u128,
     visualisation_index: usize,
+    paused: bool,
     map: GameMap,
-    history: Vec<GameMapTiles2D>,
+    history: Vec<MapSnapshot>,
+}
+
+// what the generation task hands back, populating the level needs the ecs so happens after
+pub struct GeneratedLevel {
+    map: GameMap,
+    spawn_position: Position,
+    history: Vec<MapSnapshot>,
+    // carries on from wherever the builders left it, so population rolls the same for a seed
+    level_rng: GameRNG,
+}
+
+pub struct MapGenerationTask {
+    task: Task<Result<GeneratedLevel, MapGenerationError>>,
+    recipe_name: String,
+    step_count: usize,
+    // written from the task as each recipe step finishes
+    steps_done: Arc<AtomicUsize>,
+    elapsed_ms: u128,
+    visualise: bool,
 }
 
 pub fn create_or_load_map(
     mut commands: Commands,
-    ctx: Res<ScreenContext>,
     mut rng: ResMut<GameRNG>,
+    mut dungeon_level: ResMut<DungeonLevel>,
+    settings: Res<Settings>,
 ) {
     info!("load map");
 
-    //let new_map = GameMap::new(ctx.width, ctx.height);
+    // everything about the level comes from its own seeded rng, so a seed always gives the same
+    // layout and the same population
+    dungeon_level.seed = rng.rand_u64();
+    let mut level_rng = GameRNG::seeded(dungeon_level.seed);
 
-    let mut initial_map_builder = MapBuilder::new(ctx.width, ctx.height, rng.as_mut());
+    // the recipe is picked with the level's rng too, so the seed alone is enough to rebuild it
+    let recipe = map_recipes().pick(dungeon_level.depth, &mut level_rng);
 
-    let map_builder = initial_map_builder
-        .with_generator(FillRoomGenerator::new(GameTile::UnbreakableWall))
-        .with_generator(BSPRoomMapGenerator::new())
-        .with_generator(ReplaceVisibleWallsWithBreakableMapGenerator::new())
-        .with_generator(RandomFreeSpaceSpawn::new());
+    info!(
+        "building depth {} with seed {} using recipe {}",
+        dungeon_level.depth, dungeon_level.seed, recipe.name
+    );
 
-    let new_map = map_builder.get_map();
+    let steps_done = Arc::new(AtomicUsize::new(0));
+    let task_steps_done = steps_done.clone();
+    // no point holding onto the history if nothing is going to play it back
+    let visualise = settings.visualise_map_generation;
 
-    commands.insert_resource(new_map);
+    // the builders only ever see the level's rng, so which thread runs them makes no difference
+    let task = AsyncComputeTaskPool::get().spawn(async move {
+        let on_step = |steps| task_steps_done.store(steps, Ordering::Relaxed);
+
+        // the fallback carries on with the same rng, so a failing seed still fails the same way
+        // and gets the same fallback level every time
+        let built = recipe
+            .generate(MAP_WIDTH, MAP_HEIGHT, &mut level_rng, visualise, on_step)
+            .or_else(|err| {
+                warn!(
+                    "recipe {} failed, using the fallback recipe: {}",
+                    recipe.name, err
+                );
+
+                generate_fallback(&mut level_rng, visualise, on_step)
+            })?;
+
+        Ok(GeneratedLevel {
+            map: built.map,
+            spawn_position: built.spawn_position,
+            history: built.history,
+            level_rng,
+        })
+    });
+
+    commands.insert_resource(MapGenerationTask {
+        task,
+        recipe_name: recipe.name.clone(),
+        step_count: recipe.steps.len(),
+        steps_done,
+        elapsed_ms: 0,
+        visualise,
+    });
+}
+
+// the fallback should always manage a level, but if it doesn't it gets a few more goes rather than
+// taking the game down with it. each go carries on the same rng, so it's still the same for a seed
+fn generate_fallback(
+    rng: &mut GameRNG,
+    visualise: bool,
+    on_step: impl Fn(usize),
+) -> Result<BuiltMap, MapGenerationError> {
+    let fallback = map_recipes().fallback();
+    let mut result = fallback.generate(MAP_WIDTH, MAP_HEIGHT, rng, visualise, &on_step);
+
+    for attempt in 2..=FALLBACK_ATTEMPTS {
+        let err = match result {
+            Ok(built) => return Ok(built),
+            Err(err) => err,
+        };
+
+        warn!(
+            "the fallback recipe failed, trying again ({} of {}): {}",
+            attempt, FALLBACK_ATTEMPTS, err
+        );
+
+        result = fallback.generate(MAP_WIDTH, MAP_HEIGHT, rng, visualise, &on_step);
+    }
+
+    result
+}
+
+// shows how far along generation is until the task finishes, then sets up the level from it
+pub fn handle_map_generation_task(
+    mut commands: Commands,
+    mut generation: ResMut<MapGenerationTask>,
+    mut viewport: ResMut<MapViewport>,
+    mut ctx: ResMut<ScreenContext>,
+    dungeon_level: Res<DungeonLevel>,
+    raws: Res<RawMaster>,
+    time: Res<Time>,
+) {
+    let level = match future::block_on(future::poll_once(&mut generation.task)) {
+        Some(Ok(level)) => level,
+        Some(Err(err)) => {
+            commands.remove_resource::<MapGenerationTask>();
+
+            error!(
+                "could not build depth {}, ending the run: {}",
+                dungeon_level.depth, err
+            );
+
+            // like dying, there's nowhere better than the menu to go yet
+            commands.insert_resource(NextState(GameState::InMenu {
+                menu_state: InMenuState::MainMenu,
+            }));
+
+            return;
+        }
+        None => {
+            generation.elapsed_ms += time.delta().as_millis();
+
+            draw_loading_screen(&mut ctx, &generation, dungeon_level.depth);
+
+            return;
+        }
+    };
+
+    commands.remove_resource::<MapGenerationTask>();
+
+    let GeneratedLevel {
+        map: new_map,
+        spawn_position,
+        history,
+        mut level_rng,
+    } = level;
+
+    let mut level_spawns = populate_level(
+        &new_map,
+        &spawn_position,
+        dungeon_level.depth,
+        raws.as_ref(),
+        &mut level_rng,
+    );
+
+    // anything the prefabs placed goes in alongside the random population
+    level_spawns.extend(new_map.spawn_markers.iter().cloned());
+
+    viewport.centre_on(&spawn_position, new_map.width, new_map.height);
+
+    commands.insert_resource(SpatialIndex::new(new_map.width, new_map.height));
+    commands.insert_resource(LevelSpawns {
+        spawns: level_spawns,
+    });
 
     commands.insert_resource(PlayerResource {
-        start_pos: map_builder.get_spawn_position(),
-        cur_pos: map_builder.get_spawn_position(),
+        start_pos: spawn_position.clone(),
+        cur_pos: spawn_position,
         move_waypoints: Vec::new(),
     });
 
-    commands.insert_resource(MapVisualisation {
-        tick_count_ms: 0,
-        visualisation_index: 0,
-        map: map_builder.get_map(),
-        history: map_builder.get_history(),
-    });
+    if generation.visualise {
+        commands.insert_resource(MapVisualisation {
+            tick_count_ms: 0,
+            frame_time_ms: DEFAULT_FRAME_TIME_MS,
+            visualisation_index: 0,
+            paused: false,
+            map: new_map.clone(),
+            history,
+        });
+    } else {
+        commands.insert_resource(NextState(GameState::InGame {
+            game_state: InGameState::AwaitingInput,
+        }));
+    }
+
+    commands.insert_resource(new_map);
+
+    info!(
+        "finish load map after {}ms",
+        generation.elapsed_ms + time.delta().as_millis()
+    );
+}
+
+fn draw_loading_screen(ctx: &mut ScreenContext, generation: &MapGenerationTask, depth: i32) {
+    // a dot every quarter second so it's obvious nothing has hung
+    let dots = ".".repeat((generation.elapsed_ms / 250 % 4) as usize);
+
+    let status_text = format!("Generating depth {}{}", depth, dots);
+    let progress_text = format!(
+        "{}: step {} of {}",
+        generation.recipe_name,
+        generation
+            .steps_done
+            .load(Ordering::Relaxed)
+            .min(generation.step_count),
+        generation.step_count
+    );
 
-    info!("finish load map");
+    ctx.draw_text(1, UI_PANEL_HEIGHT - 2, |b| {
+        b.with_fg_colour(Color::WHITE).with_text(&status_text)
+    });
+    ctx.draw_text(1, UI_PANEL_HEIGHT - 4, |b| {
+        b.with_fg_colour(Color::GRAY).with_text(&progress_text)
+    });
 }
 
+// space pauses, left and right step a frame at a time, up and down change the speed, and enter
+// skips straight to the level
 pub fn visualise_map(
     mut commands: Commands,
     mut ctx: ResMut<ScreenContext>,
     mut map_vis: ResMut<MapVisualisation>,
-    //map: Res<GameMap>,
+    viewport: Res<MapViewport>,
+    keyboard: Res<Input<KeyCode>>,
     time: Res<Time>,
 ) {
+    let last_index = map_vis.history.len().saturating_sub(1);
+
+    if keyboard.just_pressed(KeyCode::Space) {
+        map_vis.paused = !map_vis.paused;
+    }
+
+    if keyboard.just_pressed(KeyCode::Right) {
+        map_vis.paused = true;
+        map_vis.visualisation_index = (map_vis.visualisation_index + 1).min(last_index);
+    }
+
+    if keyboard.just_pressed(KeyCode::Left) {
+        map_vis.paused = true;
+        map_vis.visualisation_index = map_vis.visualisation_index.saturating_sub(1);
+    }
+
+    if keyboard.just_pressed(KeyCode::Up) {
+        map_vis.frame_time_ms = (map_vis.frame_time_ms / 2).max(MIN_FRAME_TIME_MS);
+    }
+
+    if keyboard.just_pressed(KeyCode::Down) {
+        map_vis.frame_time_ms = (map_vis.frame_time_ms * 2).min(MAX_FRAME_TIME_MS);
+    }
+
+    if keyboard.just_pressed(KeyCode::Return) {
+        map_vis.visualisation_index = map_vis.history.len();
+    }
+
+    if !map_vis.paused {
+        map_vis.tick_count_ms += time.delta().as_millis();
+
+        // a frame time shorter than the frame rate skips ahead more than one snapshot at a time
+        while map_vis.tick_count_ms >= map_vis.frame_time_ms {
+            map_vis.tick_count_ms -= map_vis.frame_time_ms;
+            map_vis.visualisation_index += 1;
+        }
+    }
+
     if map_vis.visualisation_index >= map_vis.history.len() {
         commands.remove_resource::<MapVisualisation>();
         commands.insert_resource(NextState(GameState::InGame {
@@ -84,72 +333,98 @@ pub fn visualise_map(
         return;
     }
 
-    map_vis.tick_count_ms += time.delta().as_millis();
+    let current_frame = &map_vis.history[map_vis.visualisation_index];
 
-    if map_vis.tick_count_ms > 50 {
-        let current_frame = &map_vis.history[map_vis.visualisation_index];
+    for view_x in 0..viewport.width {
+        for view_y in 0..viewport.height {
+            let (x, y) = (viewport.screen_x + view_x, viewport.screen_y + view_y);
 
-        for x in 0..ctx.width {
-            for y in 0..ctx.height {
-                //let mut cur_tile = ctx.get_tile(x, y);
+            let map_pos = match viewport.screen_to_map(x as i32, y as i32) {
+                Some(map_pos) if map_vis.map.is_within_bounds(map_pos.x, map_pos.y) => map_pos,
+                _ => continue,
+            };
 
-                ctx.draw_glyph(x, y, ScreenTilePriority::Map, |screen_tile| {
-                    screen_tile.glyph.char = current_frame[map_vis.map.xy_idx(x, y)].get_char_rep();
-                    screen_tile.glyph.visible = true;
-                    screen_tile.glyph.bg_color = Color::BLACK;
-                    screen_tile.glyph.fg_color = Color::WHITE;
-                });
-            }
+            ctx.draw_glyph(x, y, ScreenTilePriority::Map, |screen_tile| {
+                screen_tile.glyph.char =
+                    current_frame.tiles[map_vis.map.xy_idx_pos(&map_pos)].get_char_rep();
+                screen_tile.glyph.visible = true;
+                screen_tile.glyph.bg_color = Color::BLACK;
+                screen_tile.glyph.fg_color = Color::WHITE;
+            });
         }
-
-        map_vis.tick_count_ms = 0;
-        map_vis.visualisation_index += 1;
     }
-}
 
-pub fn finalise_map_creation(mut commands: Commands, mut map: ResMut<GameMap>) {
-    let mut tile_components = Vec::new();
-    let mut tile_components_blockers = Vec::new();
+    let frame_text = format!(
+        "{} ({} of {})",
+        current_frame.label,
+        map_vis.visualisation_index + 1,
+        map_vis.history.len()
+    );
+    let controls_text = format!(
+        "{} {}ms a frame - space pause, left/right step, up/down speed, enter skip",
+        if map_vis.paused {
+            "paused,"
+        } else {
+            "playing,"
+        },
+        map_vis.frame_time_ms
+    );
+
+    ctx.draw_text(1, UI_PANEL_HEIGHT - 2, |b| {
+        b.with_fg_colour(Color::WHITE).with_text(&frame_text)
+    });
+    ctx.draw_text(1, UI_PANEL_HEIGHT - 4, |b| {
+        b.with_fg_colour(Color::GRAY).with_text(&controls_text)
+    });
+}
 
+pub fn finalise_map_creation(
+    mut commands: Commands,
+    mut map: ResMut<GameMap>,
+    level_spawns: Res<LevelSpawns>,
+    raws: Res<RawMaster>,
+    player_res: Res<PlayerResource>,
+    mut player_query: Query<(&mut Position, &mut Viewshed), With<Player>>,
+) {
     // no longer need to hold onto probably lengthly history
     map.clear_history();
 
-    for x in 0..map.width {
-        for y in 0..map.height {
-            let game_tile = map.tiles[map.xy_idx(x, y)];
-
-            // this checks to see if the tile itself would be considered a blocker, dynamic blocking is handled elsewhere
-            if game_tile.is_blocker() {
-                tile_components_blockers.push((
-                    Position {
-                        x: x as i32,
-                        y: y as i32,
-                    },
-                    Renderable {
-                        glyph: game_tile.get_char_rep(),
-                        fg: game_tile.default_tile_colour(),
-                        bg: Color::BLACK,
-                        layer: 0.0,
-                    },
-                    Blocker {},
-                ));
-            } else {
-                tile_components.push((
-                    Position {
-                        x: x as i32,
-                        y: y as i32,
-                    },
-                    Renderable {
-                        glyph: game_tile.get_char_rep(),
-                        fg: game_tile.default_tile_colour(),
-                        bg: Color::BLACK,
-                        layer: 0.0,
-                    },
-                ));
-            }
-        }
+    // the player carries over from the level before, on the first level this is where they were
+    // spawned anyway
+    for (mut player_pos, mut viewshed) in player_query.iter_mut() {
+        *player_pos = player_res.start_pos.clone();
+        viewshed.dirty = true;
+    }
+
+    for spawn in level_spawns.spawns.iter() {
+        spawn_named_entity(
+            &mut commands,
+            raws.as_ref(),
+            &spawn.name,
+            spawn.position.clone(),
+        );
     }
 
-    commands.spawn_batch(tile_components);
-    commands.spawn_batch(tile_components_blockers);
+    commands.remove_resource::<LevelSpawns>();
+}
+
+// clears out everything that belonged to the current level apart from the player, and builds a
+// new one at the given depth
+pub fn change_depth(
+    commands: &mut Commands,
+    dungeon_level: &mut DungeonLevel,
+    depth: i32,
+    level_entities: impl IntoIterator<Item = Entity>,
+) {
+    info!("leaving depth {} for depth {}", dungeon_level.depth, depth);
+
+    for entity in level_entities {
+        commands.entity(entity).despawn_recursive();
+    }
+
+    dungeon_level.depth = depth;
+
+    commands.insert_resource(NextState(GameState::InGame {
+        game_state: InGameState::LoadMap,
+    }));
 }
@@ -3,30 +3,55 @@ use iyes_loopless::prelude::*;
 
 use crate::{GameState, InGameState};
 
-use self::update_blocking::handle_blocking_update;
+use super::resources::DungeonLevel;
+
+use self::{
+    digging::{handle_tile_changed_events, TileChangedEvent},
+    game_map::GameMap,
+    spatial_index::{handle_spatial_index_update, SpatialIndex},
+    update_blocking::handle_blocking_update,
+};
 
 pub mod builder;
 pub mod builders;
+pub mod digging;
 pub mod game_map;
-mod map_creation;
+pub mod map_creation;
 pub mod pathfinding;
+pub mod population;
+pub mod prefabs;
+pub mod recipes;
+pub mod spatial_index;
+pub mod tile_definitions;
 mod update_blocking;
 
 pub(crate) struct MapPlugin;
 
 impl Plugin for MapPlugin {
     fn build(&self, app: &mut App) {
+        app.init_resource::<DungeonLevel>();
+        app.add_event::<TileChangedEvent>();
+
         app.add_enter_system(
             GameState::InGame {
                 game_state: InGameState::LoadMap,
             },
             map_creation::create_or_load_map,
         )
+        .add_system(
+            map_creation::handle_map_generation_task
+                .run_in_state(GameState::InGame {
+                    game_state: InGameState::LoadMap,
+                })
+                .run_if_resource_exists::<map_creation::MapGenerationTask>()
+                .before("render_screen"),
+        )
         .add_system(
             map_creation::visualise_map
                 .run_in_state(GameState::InGame {
                     game_state: InGameState::LoadMap,
                 })
+                .run_if_resource_exists::<map_creation::MapVisualisation>()
                 .before("render_screen"),
         )
         .add_exit_system(
@@ -35,6 +60,13 @@ impl Plugin for MapPlugin {
             },
             map_creation::finalise_map_creation,
         )
+        .add_system(handle_tile_changed_events.run_if_resource_exists::<GameMap>())
+        // runs after everything has moved for the frame, and before the next frame's state
+        // transitions so enter systems always see an up to date index
+        .add_system_to_stage(
+            CoreStage::PostUpdate,
+            handle_spatial_index_update.run_if_resource_exists::<SpatialIndex>(),
+        )
         .add_enter_system(
             GameState::InGame {
                 game_state: InGameState::AwaitingInput,
@@ -2,7 +2,55 @@ use crate::game_logic::components::Position;
 
 use pathfinding::prelude::astar;
 
-use super::game_map::GameMap;
+use super::{
+    game_map::{GameMap, GameTile},
+    tile_definitions::Terrain,
+};
+
+// closed doors cost this much on top of the step itself, so a creature that can open them still
+// prefers an open route if there's one nearby
+const CLOSED_DOOR_EXTRA_COST: u32 = 3;
+// digging is slow, so tunnelling is only worth it when walking round is a long way
+const DIGGABLE_WALL_EXTRA_COST: u32 = 8;
+
+// what a creature is able to get through, which changes the routes open to it
+#[derive(Clone, Debug, Default)]
+pub struct PathingProfile {
+    pub opens_doors: bool,
+    pub digs: bool,
+    pub swims: bool,
+    pub flies: bool,
+    pub fire_immune: bool,
+}
+
+impl PathingProfile {
+    // terrain that would drown, burn or drop a creature like this, it won't route through it
+    pub fn avoids(&self, terrain: Terrain) -> bool {
+        match terrain {
+            Terrain::Ground | Terrain::ShallowWater => false,
+            Terrain::DeepWater => !(self.swims || self.flies),
+            Terrain::Lava => !(self.fire_immune || self.flies),
+            Terrain::Chasm => !self.flies,
+        }
+    }
+
+    // deep water is the only terrain that stops someone outright, lava and chasms can still be
+    // walked into on purpose
+    pub fn can_enter(&self, terrain: Terrain) -> bool {
+        terrain != Terrain::DeepWater || self.swims || self.flies
+    }
+
+    // wading costs walkers an extra turn
+    pub fn is_slowed_by(&self, terrain: Terrain) -> bool {
+        terrain == Terrain::ShallowWater && !(self.swims || self.flies)
+    }
+}
+
+// somewhere a creature with no special abilities, like the player, will happily walk. doors count,
+// anyone can open those
+pub fn is_walkable(tile: GameTile) -> bool {
+    (!tile.is_blocker() || tile.is_door()) && !PathingProfile::default().avoids(tile.terrain())
+}
 
 impl Position {
     fn is_valid(&self, map: &GameMap) -> bool {
@@ -25,7 +73,13 @@ impl Position {
         ((dx + dy) + (d2 - 2.) * f32::min(dx, dy))
     } */
 
-    fn successors(&self, map: &GameMap, from: &Position, to: &Position) -> Vec<(Position, u32)> {
+    fn successors(
+        &self,
+        map: &GameMap,
+        from: &Position,
+        to: &Position,
+        profile: &PathingProfile,
+    ) -> Vec<(Position, u32)> {
         let &Position { x, y } = self;
 
         let res = vec![
@@ -40,8 +94,32 @@ impl Position {
         ]
         .into_iter()
         .filter(|p| p.is_valid(map))
-        .filter(|p| !map.is_blocker(p.x, p.y) || p == from || p == to)
-        .map(|p| (p.clone(), self.distance(&p)))
+        .filter(|p| {
+            !map.is_blocker(p.x, p.y)
+                || p == from
+                || p == to
+                || (profile.opens_doors && map.is_closed_door(p.x, p.y))
+                || (profile.digs && map.is_diggable(p.x, p.y))
+        })
+        .filter(|p| p == to || !profile.avoids(map.tiles[map.xy_idx_pos(p)].terrain()))
+        .map(|p| {
+            // terrain can make a step cost more than its distance, eg wading through water
+            let tile = map.tiles[map.xy_idx_pos(&p)];
+            let movement_cost = if profile.flies {
+                1
+            } else {
+                tile.definition().movement_cost
+            };
+            let extra_cost = if map.is_closed_door(p.x, p.y) {
+                CLOSED_DOOR_EXTRA_COST
+            } else if map.is_diggable(p.x, p.y) {
+                DIGGABLE_WALL_EXTRA_COST
+            } else {
+                0
+            };
+
+            (p.clone(), self.distance(&p) * movement_cost + extra_cost)
+        })
         .collect();
 
         res
@@ -52,10 +130,11 @@ pub fn astar_next_step(
     map: &GameMap,
     from: Position,
     to: Position,
+    profile: &PathingProfile,
 ) -> Option<(Vec<Position>, u32)> {
     astar(
         &from,
-        |p| p.successors(map, &from, &to),
+        |p| p.successors(map, &from, &to, profile),
         |p| p.distance(&to),
         |p| *p == to,
     )
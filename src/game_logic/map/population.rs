@@ -0,0 +1,106 @@
+use crate::{
+    game_logic::{components::Position, raws::RawMaster},
+    rng::GameRNG,
+    utils::Rectangle,
+};
+
+use super::{game_map::GameMap, tile_definitions::Terrain};
+
+// maps without rooms are carved into square regions, each one gets its own roll for what lives there
+const REGION_SIZE: usize = 10;
+const MAX_SPAWNS_PER_REGION: i32 = 3;
+// nothing gets placed this close to where the player starts
+const MIN_SPAWN_DISTANCE: i32 = 8;
+
+#[derive(Clone, Debug)]
+pub struct SpawnEntry {
+    pub name: String,
+    pub position: Position,
+}
+
+// spawns waiting for the ecs, they're turned into entities once the map is finalised
+pub struct LevelSpawns {
+    pub spawns: Vec<SpawnEntry>,
+}
+
+pub fn populate_level(
+    map: &GameMap,
+    player_spawn: &Position,
+    depth: i32,
+    raws: &RawMaster,
+    rng: &mut GameRNG,
+) -> Vec<SpawnEntry> {
+    let spawn_table = raws.spawn_table(depth);
+    let mut spawns = Vec::new();
+
+    if spawn_table.is_empty() {
+        return spawns;
+    }
+
+    for region in spawn_regions(map) {
+        let mut candidates = Vec::new();
+
+        for (x, y) in region {
+            let (dx, dy) = (x as i32 - player_spawn.x, y as i32 - player_spawn.y);
+
+            // prefabs bring their own inhabitants, and nothing starts out stood in water or worse
+            if !map.is_within_bounds(x as i32, y as i32)
+                || map.tiles[map.xy_idx(x, y)].is_blocker()
+                || map.terrain_at(x as i32, y as i32) != Terrain::Ground
+                || map.vault_at(x, y).is_some()
+                || dx * dx + dy * dy < MIN_SPAWN_DISTANCE * MIN_SPAWN_DISTANCE
+            {
+                continue;
+            }
+
+            candidates.push(Position {
+                x: x as i32,
+                y: y as i32,
+            });
+        }
+
+        if candidates.is_empty() {
+            continue;
+        }
+
+        // deeper levels are more crowded, and some regions are left empty
+        let spawn_count = rng.rand_range_incl(-2..=MAX_SPAWNS_PER_REGION) + (depth - 1) / 2;
+
+        for _ in 0..spawn_count.min(candidates.len() as i32) {
+            // taking the tile out of the candidates means nothing ever stacks
+            let position = candidates.remove(rng.rand_range(0..candidates.len() as i32) as usize);
+
+            if let Some(name) = spawn_table.roll(rng) {
+                spawns.push(SpawnEntry { name, position });
+            }
+        }
+    }
+
+    spawns
+}
+
+// maps that know where their rooms are get one spawn roll per room, anything else is carved into
+// square regions
+fn spawn_regions(map: &GameMap) -> Vec<Vec<(usize, usize)>> {
+    if !map.rooms.is_empty() {
+        return map.rooms.iter().map(|room| room.all_squares()).collect();
+    }
+
+    let mut regions = Vec::new();
+
+    for region_y in (0..map.height).step_by(REGION_SIZE) {
+        for region_x in (0..map.width).step_by(REGION_SIZE) {
+            regions.push(
+                Rectangle {
+                    x: region_x,
+                    y: region_y,
+                    width: REGION_SIZE.min(map.width - region_x),
+                    height: REGION_SIZE.min(map.height - region_y),
+                }
+                .all_squares(),
+            );
+        }
+    }
+
+    regions
+}
@@ -0,0 +1,237 @@
+use std::{collections::HashMap, sync::OnceLock};
+
+use bevy::prelude::warn;
+
+use crate::utils::{list_assets, read_asset};
+
+use super::game_map::GameTile;
+
+const PREFABS_PATH: &str = "data/prefabs";
+
+static PREFAB_LIBRARY: OnceLock<PrefabLibrary> = OnceLock::new();
+
+#[derive(Clone, Debug, PartialEq)]
+pub enum PrefabCell {
+    // leaves whatever is already on the map alone
+    Empty,
+    Tile(GameTile),
+    // floor with the named raw spawned on top of it
+    Spawn(String),
+    // floor, and where the player starts if this is a whole level
+    PlayerSpawn,
+}
+
+#[derive(Clone, Debug)]
+pub struct Prefab {
+    pub name: String,
+    pub width: usize,
+    pub height: usize,
+    cells: Vec<PrefabCell>,
+    pub can_rotate: bool,
+    pub can_mirror: bool,
+    // only meant to be used as a whole hand made level
+    pub whole_level: bool,
+}
+
+impl Prefab {
+    // a prefab file is a header of `key = value` lines, a `---` line, then the layout itself.
+    // the header can name the prefab, turn off rotation or mirroring, and add to the legend with
+    // `tile X = TileName` or `spawn X = Raw Name`
+    fn parse(file_name: &str, prefab_str: &str) -> Result<Prefab, String> {
+        // a file can leave the header out entirely and start with the ---
+        let prefab_str = format!("\n{}", prefab_str);
+
+        let (header, layout) = match prefab_str.split_once("\n---") {
+            Some((header, layout)) => (header, layout.trim_start_matches('-')),
+            None => return Err("missing --- between the header and the layout".to_owned()),
+        };
+
+        let mut prefab = Prefab {
+            name: file_name.to_owned(),
+            width: 0,
+            height: 0,
+            cells: Vec::new(),
+            can_rotate: true,
+            can_mirror: true,
+            whole_level: false,
+        };
+
+        let mut legend = default_legend();
+
+        for line in header.lines().map(|line| line.trim()) {
+            if line.is_empty() || line.starts_with("//") {
+                continue;
+            }
+
+            let (key, value) = match line.split_once('=') {
+                Some((key, value)) => (key.trim(), value.trim()),
+                None => return Err(format!("header line without a value: {}", line)),
+            };
+
+            match key.split_whitespace().collect::<Vec<&str>>().as_slice() {
+                ["name"] => prefab.name = value.to_owned(),
+                ["rotate"] => prefab.can_rotate = value == "true",
+                ["mirror"] => prefab.can_mirror = value == "true",
+                ["level"] => prefab.whole_level = value == "true",
+                ["tile", ch] => {
+                    let tile = GameTile::by_name(value)
+                        .ok_or_else(|| format!("no tile named {}", value))?;
+
+                    legend.insert(legend_char(ch)?, PrefabCell::Tile(tile));
+                }
+                ["spawn", ch] => {
+                    legend.insert(legend_char(ch)?, PrefabCell::Spawn(value.to_owned()));
+                }
+                _ => return Err(format!("unknown header key: {}", key)),
+            }
+        }
+
+        let rows: Vec<&str> = layout
+            .lines()
+            .skip_while(|row| row.trim().is_empty())
+            .collect::<Vec<&str>>()
+            .into_iter()
+            .rev()
+            .skip_while(|row| row.trim().is_empty())
+            .collect();
+
+        prefab.height = rows.len();
+        prefab.width = rows
+            .iter()
+            .map(|row| row.chars().count())
+            .max()
+            .unwrap_or(0);
+
+        if prefab.width == 0 || prefab.height == 0 {
+            return Err("empty layout".to_owned());
+        }
+
+        // rows were reversed above, the map's y goes up the screen so the bottom row of the file
+        // is y = 0 and the prefab looks the same in game as it does in the file
+        for row in rows {
+            let mut row_chars = row.chars();
+
+            for _ in 0..prefab.width {
+                let ch = row_chars.next().unwrap_or(' ');
+
+                match legend.get(&ch) {
+                    Some(cell) => prefab.cells.push(cell.clone()),
+                    None => return Err(format!("no legend entry for '{}'", ch)),
+                }
+            }
+        }
+
+        Ok(prefab)
+    }
+
+    pub fn cell(&self, x: usize, y: usize) -> &PrefabCell {
+        &self.cells[self.width * y + x]
+    }
+
+    // quarter turns clockwise, then an optional left to right flip
+    pub fn transformed(&self, quarter_turns: usize, mirror: bool) -> Prefab {
+        let mut prefab = self.clone();
+
+        for _ in 0..(quarter_turns % 4) {
+            let mut cells = Vec::with_capacity(prefab.cells.len());
+
+            for y in 0..prefab.width {
+                for x in 0..prefab.height {
+                    cells.push(prefab.cell(prefab.width - 1 - y, x).clone());
+                }
+            }
+
+            prefab = Prefab {
+                width: prefab.height,
+                height: prefab.width,
+                cells,
+                ..prefab
+            };
+        }
+
+        if mirror {
+            let mut cells = Vec::with_capacity(prefab.cells.len());
+
+            for y in 0..prefab.height {
+                for x in 0..prefab.width {
+                    cells.push(prefab.cell(prefab.width - 1 - x, y).clone());
+                }
+            }
+
+            prefab.cells = cells;
+        }
+
+        prefab
+    }
+}
+
+fn legend_char(ch: &str) -> Result<char, String> {
+    let mut chars = ch.chars();
+
+    match (chars.next(), chars.next()) {
+        (Some(ch), None) => Ok(ch),
+        _ => Err(format!("legend keys must be a single character: {}", ch)),
+    }
+}
+
+fn default_legend() -> HashMap<char, PrefabCell> {
+    HashMap::from([
+        (' ', PrefabCell::Empty),
+        ('.', PrefabCell::Tile(GameTile::FLOOR)),
+        ('#', PrefabCell::Tile(GameTile::WALL)),
+        ('%', PrefabCell::Tile(GameTile::UNBREAKABLE_WALL)),
+        ('+', PrefabCell::Tile(GameTile::DOOR_CLOSED)),
+        ('\'', PrefabCell::Tile(GameTile::DOOR_OPEN)),
+        ('>', PrefabCell::Tile(GameTile::DOWN_STAIRS)),
+        ('<', PrefabCell::Tile(GameTile::UP_STAIRS)),
+        ('@', PrefabCell::PlayerSpawn),
+    ])
+}
+
+pub struct PrefabLibrary {
+    prefabs: Vec<Prefab>,
+}
+
+impl PrefabLibrary {
+    // every .txt file in the prefabs folder, in file name order so the same seed always picks the
+    // same prefabs. broken files are skipped rather than stopping the game
+    fn load() -> PrefabLibrary {
+        let mut prefabs = Vec::new();
+
+        for path in list_assets(PREFABS_PATH, "txt") {
+            let file_name = path
+                .rsplit('/')
+                .next()
+                .and_then(|file_name| file_name.strip_suffix(".txt"))
+                .unwrap_or_default()
+                .to_owned();
+
+            let parsed = read_asset(&path)
+                .and_then(|prefab_str| Prefab::parse(&file_name, &prefab_str.replace('\r', "")));
+
+            match parsed {
+                Ok(prefab) => prefabs.push(prefab),
+                Err(err) => warn!("could not load prefab {}: {}", path, err),
+            }
+        }
+
+        PrefabLibrary { prefabs }
+    }
+
+    pub fn get(&self, name: &str) -> Option<&Prefab> {
+        self.prefabs.iter().find(|prefab| prefab.name == name)
+    }
+
+    // the prefabs that can be dropped into a level, as opposed to being one
+    pub fn room_prefabs(&self) -> Vec<&Prefab> {
+        self.prefabs
+            .iter()
+            .filter(|prefab| !prefab.whole_level)
+            .collect()
+    }
+}
+
+// same as the tile definitions, map generation doesn't have the ecs to hand
+pub fn prefab_library() -> &'static PrefabLibrary {
+    PREFAB_LIBRARY.get_or_init(PrefabLibrary::load)
+}
@@ -0,0 +1,321 @@
+use std::sync::OnceLock;
+
+use bevy::prelude::*;
+use serde::Deserialize;
+
+use crate::{game_logic::raws::spawn_table::SpawnTable, rng::GameRNG, utils::read_asset};
+
+use super::{
+    builder::{BoxedMapGenerator, BuiltMap, MapBuilder, MapGenerationError},
+    builders::{
+        bsp::BSPRoomMapGenerator,
+        dla::{DiffusionLimitedAggregationMapGenerator, DlaMode},
+        doors::DoorPlacementMapGenerator,
+        drunkards::{DrunkardSpawnMode, DrunkardsWalkMapGenerator},
+        liquids::{LakeMapGenerator, LiquidKind, RiverMapGenerator},
+        maze::MazeMapGenerator,
+        prefab::{PrefabMapGenerator, PrefabMode},
+        spawns::RandomFreeSpaceSpawn,
+        stairs::DownStairsGenerator,
+        town::TownMapGenerator,
+        utils::{
+            FillRoomGenerator, ReplaceVisibleWallsWithBreakableMapGenerator,
+            SymmetricalMapGenerator,
+        },
+        voronoi::{DistanceMetric, VoronoiMapGenerator, VoronoiMode},
+        wfc::{WaveFunctionCollapseMapGenerator, WfcSample},
+    },
+    game_map::GameTile,
+};
+
+const MAP_RECIPES_PATH: &str = "data/map_recipes.json";
+
+static MAP_RECIPES: OnceLock<MapRecipes> = OnceLock::new();
+
+// one generator in a recipe, named by the "generator" field with the rest of the fields being its
+// parameters
+#[derive(Clone, Debug, Deserialize)]
+#[serde(tag = "generator")]
+pub enum RecipeStep {
+    Fill {
+        tile: String,
+    },
+    Bsp,
+    Doors,
+    Prefab {
+        mode: PrefabMode,
+    },
+    ReplaceVisibleWalls,
+    RandomFreeSpaceSpawn,
+    DownStairs,
+    Symmetry {
+        #[serde(default)]
+        horizontal: bool,
+        #[serde(default)]
+        vertical: bool,
+    },
+    DrunkardsWalk {
+        spawn_mode: DrunkardSpawnMode,
+        max_drunkards: usize,
+        lifetime: usize,
+        #[serde(default)]
+        brush_size: usize,
+        floor_percent: i32,
+    },
+    Dla {
+        mode: DlaMode,
+        #[serde(default)]
+        brush_size: usize,
+        floor_percent: i32,
+        #[serde(default)]
+        horizontal_symmetry: bool,
+        #[serde(default)]
+        vertical_symmetry: bool,
+    },
+    Maze {
+        river_factor: i32,
+        #[serde(default)]
+        braid_percent: i32,
+        #[serde(default)]
+        room_count: usize,
+    },
+    Voronoi {
+        seed_count: usize,
+        metric: DistanceMetric,
+        mode: VoronoiMode,
+    },
+    Wfc {
+        sample: WfcSample,
+        pattern_size: usize,
+        max_attempts: usize,
+    },
+    Town {
+        building_count: usize,
+    },
+    River {
+        kind: LiquidKind,
+        width: usize,
+    },
+    Lake {
+        kind: LiquidKind,
+        percent: i32,
+        scale: usize,
+    },
+}
+
+impl RecipeStep {
+    pub fn generator(&self) -> BoxedMapGenerator {
+        match self {
+            RecipeStep::Fill { tile } => {
+                FillRoomGenerator::new(GameTile::by_name(tile).unwrap_or_else(|| {
+                    warn!("no tile named {}, filling with unbreakable wall", tile);
+                    GameTile::UNBREAKABLE_WALL
+                }))
+            }
+            RecipeStep::Bsp => BSPRoomMapGenerator::new(),
+            RecipeStep::Doors => DoorPlacementMapGenerator::new(),
+            RecipeStep::Prefab { mode } => PrefabMapGenerator::new(mode.clone()),
+            RecipeStep::ReplaceVisibleWalls => ReplaceVisibleWallsWithBreakableMapGenerator::new(),
+            RecipeStep::RandomFreeSpaceSpawn => RandomFreeSpaceSpawn::new(),
+            RecipeStep::DownStairs => DownStairsGenerator::new(),
+            RecipeStep::Symmetry {
+                horizontal,
+                vertical,
+            } => SymmetricalMapGenerator::new(*horizontal, *vertical),
+            RecipeStep::DrunkardsWalk {
+                spawn_mode,
+                max_drunkards,
+                lifetime,
+                brush_size,
+                floor_percent,
+            } => DrunkardsWalkMapGenerator::new(
+                *spawn_mode,
+                *max_drunkards,
+                *lifetime,
+                *brush_size,
+                *floor_percent,
+            ),
+            RecipeStep::Dla {
+                mode,
+                brush_size,
+                floor_percent,
+                horizontal_symmetry,
+                vertical_symmetry,
+            } => DiffusionLimitedAggregationMapGenerator::new(
+                *mode,
+                *brush_size,
+                *floor_percent,
+                *horizontal_symmetry,
+                *vertical_symmetry,
+            ),
+            RecipeStep::Maze {
+                river_factor,
+                braid_percent,
+                room_count,
+            } => MazeMapGenerator::new(*river_factor, *braid_percent, *room_count),
+            RecipeStep::Voronoi {
+                seed_count,
+                metric,
+                mode,
+            } => VoronoiMapGenerator::new(*seed_count, *metric, mode.clone()),
+            RecipeStep::Wfc {
+                sample,
+                pattern_size,
+                max_attempts,
+            } => {
+                WaveFunctionCollapseMapGenerator::new(sample.clone(), *pattern_size, *max_attempts)
+            }
+            RecipeStep::Town { building_count } => TownMapGenerator::new(*building_count),
+            RecipeStep::River { kind, width } => RiverMapGenerator::new(*kind, *width),
+            RecipeStep::Lake {
+                kind,
+                percent,
+                scale,
+            } => LakeMapGenerator::new(*kind, *percent, *scale),
+        }
+    }
+}
+
+#[derive(Clone, Debug, Deserialize)]
+pub struct MapRecipe {
+    pub name: String,
+    // inclusive range of dungeon depths this can be picked for
+    pub depth: (i32, i32),
+    pub weight: i32,
+    pub steps: Vec<RecipeStep>,
+}
+
+impl MapRecipe {
+    // what every level was built with before recipes were data, used when the file is missing or
+    // nothing in it covers the depth
+    fn fallback() -> MapRecipe {
+        MapRecipe {
+            name: "fallback".to_owned(),
+            depth: (i32::MIN, i32::MAX),
+            weight: 1,
+            steps: vec![
+                RecipeStep::Fill {
+                    tile: "UnbreakableWall".to_owned(),
+                },
+                RecipeStep::Bsp,
+                RecipeStep::Doors,
+                RecipeStep::Prefab {
+                    mode: PrefabMode::InRooms { count: 2 },
+                },
+                RecipeStep::ReplaceVisibleWalls,
+                RecipeStep::RandomFreeSpaceSpawn,
+                RecipeStep::DownStairs,
+            ],
+        }
+    }
+
+    // on_step is told how many steps have finished after each one, for the loading screen
+    pub fn build(
+        &self,
+        map_builder: &mut MapBuilder,
+        on_step: impl Fn(usize),
+    ) -> Result<(), MapGenerationError> {
+        for (step_idx, step) in self.steps.iter().enumerate() {
+            map_builder.with_generator(step.generator())?;
+
+            on_step(step_idx + 1);
+        }
+
+        Ok(())
+    }
+
+    // runs every step on a fresh map
+    pub fn generate(
+        &self,
+        width: usize,
+        height: usize,
+        rng: &mut GameRNG,
+        record_history: bool,
+        on_step: impl Fn(usize),
+    ) -> Result<BuiltMap, MapGenerationError> {
+        let mut map_builder = MapBuilder::new(width, height, rng);
+
+        map_builder.record_history(record_history);
+
+        self.build(&mut map_builder, on_step)?;
+
+        map_builder.finish()
+    }
+}
+
+pub struct MapRecipes {
+    recipes: Vec<MapRecipe>,
+    fallback: MapRecipe,
+}
+
+impl MapRecipes {
+    fn load() -> MapRecipes {
+        let recipes: Vec<MapRecipe> = match read_asset(MAP_RECIPES_PATH) {
+            Ok(recipes_str) => match serde_json::from_str(&recipes_str) {
+                Ok(recipes) => recipes,
+                Err(err) => {
+                    warn!(
+                        "could not parse {}, using the fallback recipe: {}",
+                        MAP_RECIPES_PATH, err
+                    );
+                    Vec::new()
+                }
+            },
+            Err(err) => {
+                warn!(
+                    "could not read map recipes, using the fallback recipe: {}",
+                    err
+                );
+                Vec::new()
+            }
+        };
+
+        MapRecipes {
+            recipes: recipes
+                .into_iter()
+                .filter(|recipe| {
+                    if recipe.steps.is_empty() {
+                        warn!("map recipe {} has no steps, skipping it", recipe.name);
+                    }
+
+                    !recipe.steps.is_empty()
+                })
+                .collect(),
+            fallback: MapRecipe::fallback(),
+        }
+    }
+
+    // for when the recipe that was picked can't produce a level
+    pub fn fallback(&self) -> &MapRecipe {
+        &self.fallback
+    }
+
+    pub fn all(&self) -> &[MapRecipe] {
+        &self.recipes
+    }
+
+    pub fn get(&self, name: &str) -> Option<&MapRecipe> {
+        self.recipes.iter().find(|recipe| recipe.name == name)
+    }
+
+    // a weighted pick from the recipes that cover this depth
+    pub fn pick(&self, depth: i32, rng: &mut GameRNG) -> &MapRecipe {
+        let table = self
+            .recipes
+            .iter()
+            .filter(|recipe| depth >= recipe.depth.0 && depth <= recipe.depth.1)
+            .fold(SpawnTable::new(), |table, recipe| {
+                table.with_entry(&recipe.name, recipe.weight)
+            });
+
+        table
+            .roll(rng)
+            .and_then(|name| self.get(&name))
+            .unwrap_or(&self.fallback)
+    }
+}
+
+// same as the tile definitions, map generation doesn't have the ecs to hand
+pub fn map_recipes() -> &'static MapRecipes {
+    MAP_RECIPES.get_or_init(MapRecipes::load)
+}
@@ -0,0 +1,150 @@
+use std::collections::HashMap;
+
+use bevy::prelude::*;
+
+use crate::game_logic::components::{Blocker, Position};
+
+// per map lookup of which entities are on which tile, so nothing has to walk a whole query to find
+// out what's standing somewhere
+pub struct SpatialIndex {
+    width: usize,
+    height: usize,
+    tile_entities: Vec<Vec<(Entity, bool)>>,
+    entity_tiles: HashMap<Entity, usize>,
+}
+
+impl SpatialIndex {
+    pub fn new(width: usize, height: usize) -> SpatialIndex {
+        SpatialIndex {
+            width,
+            height,
+            tile_entities: vec![Vec::new(); width * height],
+            entity_tiles: HashMap::new(),
+        }
+    }
+
+    fn xy_idx(&self, x: usize, y: usize) -> usize {
+        self.width * y + x
+    }
+
+    fn is_within_bounds(&self, x: i32, y: i32) -> bool {
+        x >= 0 && y >= 0 && x < self.width as i32 && y < self.height as i32
+    }
+
+    pub fn insert(&mut self, entity: Entity, position: &Position, is_blocker: bool) {
+        self.remove(entity);
+
+        if !self.is_within_bounds(position.x, position.y) {
+            return;
+        }
+
+        let idx = self.xy_idx(position.x as usize, position.y as usize);
+
+        self.tile_entities[idx].push((entity, is_blocker));
+        self.entity_tiles.insert(entity, idx);
+    }
+
+    // for when something starts or stops blocking without moving
+    pub fn set_blocker(&mut self, entity: Entity, is_blocker: bool) {
+        if let Some(idx) = self.entity_tiles.get(&entity) {
+            for (tile_entity, tile_is_blocker) in self.tile_entities[*idx].iter_mut() {
+                if *tile_entity == entity {
+                    *tile_is_blocker = is_blocker;
+                }
+            }
+        }
+    }
+
+    pub fn remove(&mut self, entity: Entity) {
+        if let Some(idx) = self.entity_tiles.remove(&entity) {
+            self.tile_entities[idx].retain(|(e, _)| *e != entity);
+        }
+    }
+
+    pub fn entities_at(&self, x: i32, y: i32) -> Vec<Entity> {
+        if !self.is_within_bounds(x, y) {
+            return Vec::new();
+        }
+
+        self.tile_entities[self.xy_idx(x as usize, y as usize)]
+            .iter()
+            .map(|(entity, _)| *entity)
+            .collect()
+    }
+
+    pub fn blocker_at(&self, x: i32, y: i32) -> Option<Entity> {
+        if !self.is_within_bounds(x, y) {
+            return None;
+        }
+
+        self.tile_entities[self.xy_idx(x as usize, y as usize)]
+            .iter()
+            .find(|(_, is_blocker)| *is_blocker)
+            .map(|(entity, _)| *entity)
+    }
+
+    pub fn entities_within_radius(
+        &self,
+        center: &Position,
+        radius: i32,
+    ) -> Vec<(Entity, Position)> {
+        let mut res = Vec::new();
+
+        for y in (center.y - radius)..=(center.y + radius) {
+            for x in (center.x - radius)..=(center.x + radius) {
+                let (dx, dy) = (x - center.x, y - center.y);
+
+                if dx * dx + dy * dy > radius * radius || !self.is_within_bounds(x, y) {
+                    continue;
+                }
+
+                for (entity, _) in self.tile_entities[self.xy_idx(x as usize, y as usize)].iter() {
+                    res.push((*entity, Position { x, y }));
+                }
+            }
+        }
+
+        res
+    }
+
+    pub fn all_blockers(&self) -> Vec<Position> {
+        self.entity_tiles
+            .values()
+            .filter(|idx| {
+                self.tile_entities[**idx]
+                    .iter()
+                    .any(|(_, is_blocker)| *is_blocker)
+            })
+            .map(|idx| Position {
+                x: (idx % self.width) as i32,
+                y: (idx / self.width) as i32,
+            })
+            .collect()
+    }
+}
+
+// movement systems keep the index up to date as they go, this catches everything else, like
+// spawns, despawns and blockers being added or taken away
+pub fn handle_spatial_index_update(
+    mut spatial_index: ResMut<SpatialIndex>,
+    moved_query: Query<(Entity, &Position, Option<&Blocker>), Changed<Position>>,
+    new_blocker_query: Query<Entity, (Changed<Blocker>, With<Position>)>,
+    removed_positions: RemovedComponents<Position>,
+    removed_blockers: RemovedComponents<Blocker>,
+) {
+    for entity in removed_positions.iter() {
+        spatial_index.remove(entity);
+    }
+
+    for entity in removed_blockers.iter() {
+        spatial_index.set_blocker(entity, false);
+    }
+
+    for (entity, position, blocker_opt) in moved_query.iter() {
+        spatial_index.insert(entity, position, blocker_opt.is_some());
+    }
+
+    for entity in new_blocker_query.iter() {
+        spatial_index.set_blocker(entity, true);
+    }
+}
@@ -0,0 +1,264 @@
+use std::{collections::HashMap, sync::OnceLock};
+
+use bevy::prelude::{warn, Color};
+use serde::Deserialize;
+
+use crate::utils::read_asset;
+
+use super::game_map::GameTile;
+
+const TILE_DEFINITIONS_PATH: &str = "data/tiles.json";
+
+static TILE_DEFINITIONS: OnceLock<TileDefinitions> = OnceLock::new();
+
+// glyphs can be given as a character or as a code page 437 index for the ones that have no
+// sensible unicode equivalent
+#[derive(Clone, Debug, Deserialize)]
+#[serde(untagged)]
+enum GlyphDefinition {
+    Code(u16),
+    Char(char),
+}
+
+// what standing in a tile does to a creature, anything that isn't plain ground is something only
+// some creatures will cross
+#[derive(Clone, Copy, Debug, Default, PartialEq, Eq, Deserialize)]
+pub enum Terrain {
+    #[default]
+    Ground,
+    // slows walkers down and puts out fires
+    ShallowWater,
+    // only swimmers and fliers get through
+    DeepWater,
+    // burns anything that isn't immune
+    Lava,
+    // drops whatever walks in to the next level down
+    Chasm,
+}
+
+#[derive(Clone, Debug, Deserialize)]
+struct RawTileDefinition {
+    name: String,
+    glyph: GlyphDefinition,
+    fg: String,
+    #[serde(default = "default_bg")]
+    bg: String,
+    #[serde(default)]
+    blocks_movement: bool,
+    #[serde(default)]
+    blocks_sight: bool,
+    #[serde(default = "default_movement_cost")]
+    movement_cost: u32,
+    #[serde(default)]
+    diggable: bool,
+    #[serde(default)]
+    connects_walls: bool,
+    #[serde(default)]
+    terrain: Terrain,
+    #[serde(default)]
+    description: String,
+}
+
+fn default_bg() -> String {
+    "000000".to_owned()
+}
+
+fn default_movement_cost() -> u32 {
+    1
+}
+
+#[derive(Clone, Debug)]
+pub struct TileDefinition {
+    pub name: String,
+    pub glyph: u16,
+    pub fg: Color,
+    pub bg: Color,
+    pub blocks_movement: bool,
+    pub blocks_sight: bool,
+    pub movement_cost: u32,
+    pub diggable: bool,
+    // drawn with the line drawing glyphs that join up with neighbouring tiles that also connect
+    pub connects_walls: bool,
+    pub terrain: Terrain,
+    pub description: String,
+}
+
+impl TileDefinition {
+    fn new(name: &str, glyph: u16, fg: Color, blocks: bool, diggable: bool) -> TileDefinition {
+        TileDefinition {
+            name: name.to_owned(),
+            glyph,
+            fg,
+            bg: Color::BLACK,
+            blocks_movement: blocks,
+            blocks_sight: blocks,
+            movement_cost: 1,
+            diggable,
+            connects_walls: false,
+            terrain: Terrain::Ground,
+            description: String::new(),
+        }
+    }
+
+    fn liquid(name: &str, glyph: u16, fg: Color, bg: Color, terrain: Terrain) -> TileDefinition {
+        TileDefinition {
+            bg,
+            blocks_sight: false,
+            terrain,
+            ..TileDefinition::new(name, glyph, fg, false, false)
+        }
+    }
+}
+
+pub struct TileDefinitions {
+    definitions: Vec<TileDefinition>,
+    ids_by_name: HashMap<String, GameTile>,
+}
+
+impl TileDefinitions {
+    // the built in tiles always occupy the first ids, in the same order as the consts on GameTile
+    fn defaults() -> TileDefinitions {
+        let mut wall = TileDefinition::new("Wall", '#' as u16, Color::BEIGE, true, true);
+        wall.connects_walls = true;
+
+        let mut shallow_water = TileDefinition::liquid(
+            "ShallowWater",
+            '~' as u16,
+            Color::rgb(0.53, 0.81, 0.98),
+            Color::BLACK,
+            Terrain::ShallowWater,
+        );
+        shallow_water.movement_cost = 2;
+
+        let definitions = vec![
+            TileDefinition::new("Floor", '.' as u16, Color::GRAY, false, false),
+            wall,
+            TileDefinition::new("UnbreakableWall", 178, Color::MIDNIGHT_BLUE, true, false),
+            TileDefinition::new("DownStairs", 31, Color::RED, false, false),
+            TileDefinition::new("UpStairs", 30, Color::GREEN, false, false),
+            TileDefinition::new(
+                "DoorClosed",
+                '+' as u16,
+                Color::rgb(0.55, 0.27, 0.07),
+                true,
+                false,
+            ),
+            TileDefinition::new(
+                "DoorOpen",
+                '\'' as u16,
+                Color::rgb(0.55, 0.27, 0.07),
+                false,
+                false,
+            ),
+            TileDefinition::new("Grass", '"' as u16, Color::DARK_GREEN, false, false),
+            TileDefinition::new(
+                "Road",
+                '.' as u16,
+                Color::rgb(0.82, 0.71, 0.55),
+                false,
+                false,
+            ),
+            TileDefinition::liquid(
+                "DeepWater",
+                247,
+                Color::BLUE,
+                Color::rgb(0.0, 0.0, 0.3),
+                Terrain::DeepWater,
+            ),
+            shallow_water,
+            TileDefinition::liquid(
+                "Lava",
+                247,
+                Color::ORANGE_RED,
+                Color::rgb(0.4, 0.0, 0.0),
+                Terrain::Lava,
+            ),
+            TileDefinition::liquid("Chasm", 176, Color::DARK_GRAY, Color::BLACK, Terrain::Chasm),
+        ];
+
+        let ids_by_name = definitions
+            .iter()
+            .enumerate()
+            .map(|(id, definition)| (definition.name.clone(), GameTile(id as u16)))
+            .collect();
+
+        TileDefinitions {
+            definitions,
+            ids_by_name,
+        }
+    }
+
+    // entries named after an existing tile replace it, anything else is added on the end
+    fn load() -> TileDefinitions {
+        let mut tile_definitions = TileDefinitions::defaults();
+
+        let raw_definitions: Vec<RawTileDefinition> = match read_asset(TILE_DEFINITIONS_PATH) {
+            Ok(definitions_str) => match serde_json::from_str(&definitions_str) {
+                Ok(raw_definitions) => raw_definitions,
+                Err(err) => {
+                    warn!(
+                        "could not parse {}, using default tiles: {}",
+                        TILE_DEFINITIONS_PATH, err
+                    );
+                    Vec::new()
+                }
+            },
+            Err(err) => {
+                warn!(
+                    "could not read tile definitions, using default tiles: {}",
+                    err
+                );
+                Vec::new()
+            }
+        };
+
+        for raw_definition in raw_definitions {
+            let definition = TileDefinition {
+                name: raw_definition.name.clone(),
+                glyph: match raw_definition.glyph {
+                    GlyphDefinition::Code(code) => code,
+                    GlyphDefinition::Char(ch) => ch as u16,
+                },
+                fg: Color::hex(&raw_definition.fg).unwrap_or(Color::WHITE),
+                bg: Color::hex(&raw_definition.bg).unwrap_or(Color::BLACK),
+                blocks_movement: raw_definition.blocks_movement,
+                blocks_sight: raw_definition.blocks_sight,
+                movement_cost: raw_definition.movement_cost.max(1),
+                diggable: raw_definition.diggable,
+                connects_walls: raw_definition.connects_walls,
+                terrain: raw_definition.terrain,
+                description: raw_definition.description,
+            };
+
+            match tile_definitions.ids_by_name.get(&raw_definition.name) {
+                Some(existing_tile) => {
+                    tile_definitions.definitions[existing_tile.0 as usize] = definition
+                }
+                None => {
+                    let new_tile = GameTile(tile_definitions.definitions.len() as u16);
+
+                    tile_definitions
+                        .ids_by_name
+                        .insert(raw_definition.name, new_tile);
+                    tile_definitions.definitions.push(definition);
+                }
+            }
+        }
+
+        tile_definitions
+    }
+
+    pub fn get(&self, tile: GameTile) -> &TileDefinition {
+        &self.definitions[tile.0 as usize]
+    }
+
+    pub fn by_name(&self, name: &str) -> Option<GameTile> {
+        self.ids_by_name.get(name).copied()
+    }
+}
+
+// loaded the first time anything asks for a tile property, map generation can happen before the
+// ecs is up so this lives outside of it
+pub fn tile_definitions() -> &'static TileDefinitions {
+    TILE_DEFINITIONS.get_or_init(TileDefinitions::load)
+}
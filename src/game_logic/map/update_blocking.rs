@@ -3,8 +3,7 @@ use iyes_loopless::state::NextState;
 
 use crate::{
     game_logic::{
-        components::{Blocker, Monster, Position},
-        map::{game_map::GameMap, pathfinding::astar_next_step},
+        map::{game_map::GameMap, spatial_index::SpatialIndex},
         resources::PlayerResource,
     },
     GameState, InGameState,
@@ -14,12 +13,13 @@ pub fn handle_blocking_update(
     mut commands: Commands,
     player_res: Res<PlayerResource>,
     mut map: ResMut<GameMap>,
-    blocker_position_query: Query<&Position, With<Blocker>>,
+    spatial_index: Res<SpatialIndex>,
 ) {
-    let mut new_blockers = vec![false; map.width * map.height];
+    // terrain blocks on its own, entities with a blocker component are layered on top
+    let mut new_blockers: Vec<bool> = map.tiles.iter().map(|tile| tile.is_blocker()).collect();
 
-    for blocker_pos in blocker_position_query.iter() {
-        new_blockers[map.xy_idx_pos(blocker_pos)] = true;
+    for blocker_pos in spatial_index.all_blockers() {
+        new_blockers[map.xy_idx_pos(&blocker_pos)] = true;
     }
 
     map.blocking_tiles = new_blockers;
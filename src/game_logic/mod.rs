@@ -3,23 +3,43 @@ use iyes_loopless::prelude::*;
 
 use crate::{GameState, InGameState};
 
-use self::{rendering::handle_renderable, viewshed::handle_viewshed_updating};
+use self::{
+    hazards::handle_terrain_effects,
+    raws::RawMaster,
+    rendering::{handle_renderable, handle_viewport_follow},
+    resources::PlayerResource,
+    viewshed::handle_viewshed_updating,
+};
 
 pub mod components;
-mod map;
+mod hazards;
+pub mod map;
 mod monster;
 mod player;
+pub mod raws;
 mod rendering;
 mod resources;
 mod viewshed;
 
-pub(crate) struct GameLogicPlugin;
+pub struct GameLogicPlugin;
 //handle_viewshed_updating
 impl Plugin for GameLogicPlugin {
     fn build(&self, app: &mut App) {
-        app.add_plugin(player::PlayerPlugin)
+        app.insert_resource(RawMaster::load())
+            .add_plugin(player::PlayerPlugin)
             .add_plugin(monster::MonsterPlugin)
             .add_plugin(map::MapPlugin) //
+            .add_enter_system(
+                GameState::InGame {
+                    game_state: InGameState::AwaitingInput,
+                },
+                handle_terrain_effects,
+            )
+            .add_system(
+                handle_viewport_follow
+                    .run_if_resource_exists::<PlayerResource>()
+                    .before("renderable_system"),
+            )
             .add_system(
                 handle_renderable
                     .run_if(
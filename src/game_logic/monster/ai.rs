@@ -3,43 +3,162 @@ use iyes_loopless::state::NextState;
 
 use crate::{
     game_logic::{
-        components::{Blocker, Monster, Position},
-        map::{game_map::GameMap, pathfinding::astar_next_step},
-        resources::PlayerResource,
+        components::{
+            Ai, AiType, Blocker, Digger, DoorOpener, FireImmune, Flier, Monster, Player, Position,
+            Slowed, Swimmer, Viewshed,
+        },
+        map::{
+            digging::{dig_at, TileChangedEvent},
+            game_map::GameMap,
+            pathfinding::{astar_next_step, PathingProfile},
+            spatial_index::SpatialIndex,
+        },
     },
+    rng::GameRNG,
     GameState, InGameState,
 };
 
+// kept apart from the monsters' query so both can be borrowed at once
+type PlayerQuery<'w, 's> =
+    Query<'w, 's, (&'static Position, &'static mut Viewshed), (With<Player>, Without<Monster>)>;
+
+// everything a monster's move depends on, the movement abilities make up its pathing profile
+type MonsterQuery<'w, 's> = Query<
+    'w,
+    's,
+    (
+        Entity,
+        &'static mut Position,
+        Option<&'static Ai>,
+        Option<&'static DoorOpener>,
+        Option<&'static mut Digger>,
+        (
+            Option<&'static Swimmer>,
+            Option<&'static Flier>,
+            Option<&'static FireImmune>,
+        ),
+        Option<&'static Slowed>,
+        Option<&'static Blocker>,
+    ),
+    With<Monster>,
+>;
+
 pub fn handle_monster_movement(
     mut commands: Commands,
-    player_res: Res<PlayerResource>,
-    map: Res<GameMap>,
-
-    mut mon_pos_query: Query<(Entity, &mut Position), With<Monster>>,
+    mut map: ResMut<GameMap>,
+    mut spatial_index: ResMut<SpatialIndex>,
+    mut rng: ResMut<GameRNG>,
+    mut mon_pos_query: MonsterQuery,
+    mut player_query: PlayerQuery,
+    mut tile_changed_events: EventWriter<TileChangedEvent>,
 ) {
-    println!("handle mosnter");
+    let mut door_opened = false;
+
+    let player_pos = match player_query.get_single() {
+        Ok((player_pos, _)) => player_pos.clone(),
+        Err(_) => return,
+    };
+
+    for (
+        entity,
+        mut mon_pos,
+        ai_opt,
+        door_opener_opt,
+        mut digger_opt,
+        (swimmer_opt, flier_opt, fire_immune_opt),
+        slowed_opt,
+        blocker_opt,
+    ) in mon_pos_query.iter_mut()
+    {
+        let profile = PathingProfile {
+            opens_doors: door_opener_opt.is_some(),
+            digs: digger_opt.is_some(),
+            swims: swimmer_opt.is_some(),
+            flies: flier_opt.is_some(),
+            fire_immune: fire_immune_opt.is_some(),
+        };
+
+        // still wading out of the water it stepped into last turn
+        if slowed_opt.is_some() {
+            commands.entity(entity).remove::<Slowed>();
+            continue;
+        }
+
+        // monsters without an ai set chase, which is what they all used to do
+        match ai_opt.map(|ai| ai.ai_type).unwrap_or(AiType::Chase) {
+            AiType::Chase => {}
+            AiType::Static => continue,
+            AiType::Wander => {
+                let (dx, dy) = match rng.rand_range(0..4) {
+                    0 => (0, 1),
+                    1 => (1, 0),
+                    2 => (0, -1),
+                    _ => (-1, 0),
+                };
+
+                let (new_x, new_y) = (mon_pos.x + dx, mon_pos.y + dy);
+
+                if profile.opens_doors && map.open_door(new_x, new_y) {
+                    door_opened = true;
+                } else if map.is_within_bounds(new_x, new_y)
+                    && !map.is_blocker(new_x, new_y)
+                    && spatial_index.blocker_at(new_x, new_y).is_none()
+                    && !profile.avoids(map.terrain_at(new_x, new_y))
+                {
+                    *mon_pos = Position { x: new_x, y: new_y };
+                    // so the rest of the monsters moving this turn don't walk into it
+                    spatial_index.insert(entity, &mon_pos, blocker_opt.is_some());
 
-    for (entity, mut mon_pos) in mon_pos_query.iter_mut() {
-        let next_step_opt = astar_next_step(&map, mon_pos.clone(), player_res.cur_pos.clone());
+                    if profile.is_slowed_by(map.terrain_at(new_x, new_y)) {
+                        commands.entity(entity).insert(Slowed {});
+                    }
+                }
+
+                continue;
+            }
+        }
+
+        let next_step_opt = astar_next_step(&map, mon_pos.clone(), player_pos.clone(), &profile);
 
         if let Some((mut next_step_vec, _)) = next_step_opt {
-            
             if next_step_vec.len() >= 2 {
                 // discard the head, it is itself
                 next_step_vec.remove(0);
 
                 let next_pos = next_step_vec[0].clone();
 
-                println!("cur pos {} {}", mon_pos.x, mon_pos.y);
-                println!("next {} {}", next_pos.x, next_pos.y);
-
-                if !map.is_blocker(next_pos.x, next_pos.y) {
+                // opening a door takes the whole move, they step through next turn
+                if profile.opens_doors && map.open_door(next_pos.x, next_pos.y) {
+                    door_opened = true;
+                } else if let Some(digger) = digger_opt
+                    .as_mut()
+                    .filter(|_| map.is_diggable(next_pos.x, next_pos.y))
+                {
+                    // same for digging, except a wall takes a few turns to come down
+                    if dig_at(&mut map, digger, next_pos.x, next_pos.y) {
+                        tile_changed_events.send(TileChangedEvent { position: next_pos });
+                    }
+                } else if !map.is_blocker(next_pos.x, next_pos.y)
+                    && spatial_index.blocker_at(next_pos.x, next_pos.y).is_none()
+                {
                     *mon_pos = next_step_vec[0].clone();
+                    spatial_index.insert(entity, &mon_pos, blocker_opt.is_some());
+
+                    if profile.is_slowed_by(map.terrain_at(next_pos.x, next_pos.y)) {
+                        commands.entity(entity).insert(Slowed {});
+                    }
                 }
             }
         }
     }
 
+    // the player might be able to see through whatever was just opened
+    if door_opened {
+        for (_, mut viewshed) in player_query.iter_mut() {
+            viewshed.dirty = true;
+        }
+    }
+
     commands.insert_resource(NextState(GameState::InGame {
         game_state: InGameState::AwaitingInput,
     }));
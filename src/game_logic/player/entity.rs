@@ -5,7 +5,7 @@ use iyes_loopless::state::NextState;
 
 use crate::{
     game_logic::{
-        components::{Blocker, Monster, Player, Position, Renderable, Viewshed},
+        components::{Blocker, DoorOpener, Player, Position, Renderable, Stats, Viewshed},
         map::{game_map::GameMap, pathfinding::astar_next_step},
         resources::PlayerResource,
     },
@@ -13,6 +13,10 @@ use crate::{
     GameState, InGameState,
 };
 
+const PLAYER_MAX_HP: i32 = 30;
+const PLAYER_POWER: i32 = 5;
+const PLAYER_DEFENSE: i32 = 2;
+
 pub fn setup_player(mut commands: Commands, player_res: Res<PlayerResource>) {
     commands
         .spawn()
@@ -25,23 +29,13 @@ pub fn setup_player(mut commands: Commands, player_res: Res<PlayerResource>) {
             layer: 100.0,
         })
         .insert(Blocker {})
-        .insert(Viewshed {
-            dirty: true,
-            distance: 8,
-            visible_tiles: HashSet::new(),
-        });
-
-    commands
-        .spawn()
-        .insert(Monster {})
-        .insert(player_res.start_pos.clone())
-        .insert(Renderable {
-            glyph: 'g' as u16,
-            fg: Color::RED,
-            bg: Color::BLACK,
-            layer: 50.0,
+        .insert(DoorOpener {})
+        .insert(Stats {
+            max_hp: PLAYER_MAX_HP,
+            hp: PLAYER_MAX_HP,
+            power: PLAYER_POWER,
+            defense: PLAYER_DEFENSE,
         })
-        .insert(Blocker {})
         .insert(Viewshed {
             dirty: true,
             distance: 8,
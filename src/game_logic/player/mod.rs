@@ -20,6 +20,14 @@ impl Plugin for PlayerPlugin {
                     game_state: InGameState::AwaitingInput,
                 }),
             )
+            .add_system(movement::handle_close_door.run_in_state(GameState::InGame {
+                game_state: InGameState::AwaitingInput,
+            }))
+            .add_system(
+                movement::handle_descend_stairs.run_in_state(GameState::InGame {
+                    game_state: InGameState::AwaitingInput,
+                }),
+            )
             .add_system(entity::handle_player_turn.run_in_state(GameState::InGame {
                 game_state: InGameState::PlayerTurn,
             }))
@@ -4,11 +4,20 @@ use iyes_loopless::prelude::*;
 use crate::{
     camera::MousePositionOnScreen,
     game_logic::{
-        components::{Blocker, Player, Position, Viewshed},
-        map::{game_map::GameMap, pathfinding::astar_next_step},
-        resources::PlayerResource,
+        components::{Digger, DiggingTool, Player, Position, Slowed, Viewshed},
+        map::{
+            digging::{dig_at, TileChangedEvent},
+            game_map::{GameMap, GameTile},
+            map_creation::change_depth,
+            pathfinding::{astar_next_step, PathingProfile},
+            spatial_index::SpatialIndex,
+        },
+        resources::{DungeonLevel, PlayerResource},
+    },
+    screen::{
+        structs::{ScreenContext, ScreenTilePriority},
+        viewport::MapViewport,
     },
-    screen::structs::{ScreenContext, ScreenTilePriority},
     GameState, InGameState,
 };
 
@@ -26,14 +35,19 @@ pub struct WaypointCounter {
 pub fn handle_player_movement(
     mut commands: Commands,
     keyboard: Res<Input<KeyCode>>,
-    mut ctx: ResMut<ScreenContext>,
     time: Res<Time>,
-    map: Res<GameMap>,
+    mut map: ResMut<GameMap>,
     mut held_counter: Local<HeldCounter>,
     mut waypoint_counter: Local<WaypointCounter>,
+    mut spatial_index: ResMut<SpatialIndex>,
     mut player_res: ResMut<PlayerResource>,
-    mut player_position_query: Query<(Entity, &mut Position, &mut Viewshed), With<Player>>,
-    blocker_position_query: Query<(Entity, &Position), (With<Blocker>, Without<Player>)>,
+    mut player_position_query: Query<
+        (Entity, &mut Position, &mut Viewshed, Option<&mut Digger>),
+        With<Player>,
+    >,
+    slowed_query: Query<(), (With<Slowed>, With<Player>)>,
+    digging_tool_query: Query<&DiggingTool>,
+    mut tile_changed_events: EventWriter<TileChangedEvent>,
 ) {
     let (mut direction_x, mut direction_y) =
         if keyboard.pressed(KeyCode::W) || keyboard.pressed(KeyCode::Up) {
@@ -67,33 +81,73 @@ pub fn handle_player_movement(
         }
     }
 
-    let (_entity, mut player_pos, mut viewshed) = player_position_query.single_mut();
+    let (player_entity, mut player_pos, mut viewshed, mut digger_opt) =
+        player_position_query.single_mut();
+    // the player can't swim, but can still jump into lava or a chasm if they really mean to
+    let profile = PathingProfile::default();
 
     let new_x = player_pos.x + direction_x;
     let new_y = player_pos.y + direction_y;
 
-    for (_entity, position) in blocker_position_query.iter() {
-        if position.x == new_x && position.y == new_y {
-            return;
-        }
+    // walking into a closed door opens it, which uses up the move
+    let opening_door = map.is_closed_door(new_x, new_y);
+    // and walking into a diggable wall with something to dig with chips away at it
+    let digging = digger_opt.is_some() && map.is_diggable(new_x, new_y);
+
+    if !opening_door
+        && !digging
+        && map.is_within_bounds(new_x, new_y)
+        && (map.is_blocker(new_x, new_y)
+            || spatial_index.blocker_at(new_x, new_y).is_some()
+            || !profile.can_enter(map.terrain_at(new_x, new_y)))
+    {
+        return;
     }
 
-    if new_x >= 0
-        && new_y >= 0
-        && new_x < ctx.width as i32
-        && new_y < ctx.height as i32
+    if map.is_within_bounds(new_x, new_y)
         && held_counter.counter_ms == 0
         && (direction_x != 0 || direction_y != 0)
     {
-        player_pos.x = new_x;
-        player_pos.y = new_y;
+        if slowed_query.contains(player_entity) {
+            // the whole move goes on wading out
+            commands.entity(player_entity).remove::<Slowed>();
+        } else if opening_door {
+            map.open_door(new_x, new_y);
+        } else if let (true, Some(digger)) = (digging, digger_opt.as_mut()) {
+            if dig_at(&mut map, digger, new_x, new_y) {
+                tile_changed_events.send(TileChangedEvent {
+                    position: Position { x: new_x, y: new_y },
+                });
+            }
+        } else {
+            player_pos.x = new_x;
+            player_pos.y = new_y;
 
-        player_res.cur_pos = Position { x: new_x, y: new_y };
+            player_res.cur_pos = Position { x: new_x, y: new_y };
+            // the player always blocks, monsters moving after this need to see where they are now
+            spatial_index.insert(player_entity, &player_pos, true);
 
-        if !player_res.move_waypoints.is_empty() {
-            if waypoint_counter.counter_ms > 200 {
-                player_res.move_waypoints.remove(0);
-                waypoint_counter.counter_ms = 0;
+            if profile.is_slowed_by(map.terrain_at(new_x, new_y)) {
+                commands.entity(player_entity).insert(Slowed {});
+            }
+
+            // any digging tool lying here gets picked up straight away
+            for entity in spatial_index.entities_at(new_x, new_y) {
+                if let Ok(digging_tool) = digging_tool_query.get(entity) {
+                    info!("picked up a digging tool");
+
+                    commands
+                        .entity(player_entity)
+                        .insert(Digger::new(digging_tool.dig_turns));
+                    commands.entity(entity).despawn();
+                }
+            }
+
+            if !player_res.move_waypoints.is_empty() {
+                if waypoint_counter.counter_ms > 200 {
+                    player_res.move_waypoints.remove(0);
+                    waypoint_counter.counter_ms = 0;
+                }
             }
         }
 
@@ -119,6 +173,77 @@ pub fn handle_player_movement(
     }
 }
 
+// closes an open door next to the player, as long as nothing is standing in the doorway
+pub fn handle_close_door(
+    mut commands: Commands,
+    keyboard: Res<Input<KeyCode>>,
+    mut map: ResMut<GameMap>,
+    spatial_index: Res<SpatialIndex>,
+    mut player_query: Query<(&Position, &mut Viewshed), With<Player>>,
+) {
+    if !keyboard.just_pressed(KeyCode::C) {
+        return;
+    }
+
+    let (player_pos, mut viewshed) = player_query.single_mut();
+
+    for (dx, dy) in [
+        (0, 1),
+        (1, 0),
+        (0, -1),
+        (-1, 0),
+        (1, 1),
+        (-1, 1),
+        (1, -1),
+        (-1, -1),
+    ] {
+        let (x, y) = (player_pos.x + dx, player_pos.y + dy);
+
+        if map.is_open_door(x, y) && spatial_index.entities_at(x, y).is_empty() {
+            map.close_door(x, y);
+
+            viewshed.dirty = true;
+
+            commands.insert_resource(NextState(GameState::InGame {
+                game_state: InGameState::PlayerTurn,
+            }));
+
+            return;
+        }
+    }
+}
+
+// > while standing on down stairs
+pub fn handle_descend_stairs(
+    mut commands: Commands,
+    keyboard: Res<Input<KeyCode>>,
+    map: Res<GameMap>,
+    mut dungeon_level: ResMut<DungeonLevel>,
+    player_query: Query<&Position, With<Player>>,
+    level_entity_query: Query<Entity, (With<Position>, Without<Player>)>,
+) {
+    let shift = keyboard.pressed(KeyCode::LShift) || keyboard.pressed(KeyCode::RShift);
+
+    if !(shift && keyboard.just_pressed(KeyCode::Period)) {
+        return;
+    }
+
+    let player_pos = player_query.single();
+
+    if map.tiles[map.xy_idx_pos(player_pos)] != GameTile::DOWN_STAIRS {
+        return;
+    }
+
+    let depth = dungeon_level.depth + 1;
+
+    change_depth(
+        &mut commands,
+        &mut dungeon_level,
+        depth,
+        level_entity_query.iter(),
+    );
+}
+
 pub fn handle_mouse_movement(
     mut player_res: ResMut<PlayerResource>,
     map: Res<GameMap>,
@@ -126,15 +251,29 @@ pub fn handle_mouse_movement(
     mut mouse_res: ResMut<MousePositionOnScreen>,
     mut pathfinding_history: Local<Vec<Position>>,
     buttons: Res<Input<MouseButton>>,
+    viewport: Res<MapViewport>,
+    player_digger_query: Query<Option<&Digger>, With<Player>>,
 ) {
     // if the mouse res changed, calculate a route and store it
 
     if mouse_res.is_changed() || player_res.is_changed() {
-        if let Some(mouse_pos_map) = &mouse_res.mouse_pos_map_opt {
+        // the mouse position is in screen tiles, it needs to go through the viewport to be on the map
+        let mouse_map_pos_opt = mouse_res
+            .mouse_pos_map_opt
+            .as_ref()
+            .and_then(|mouse_pos| viewport.screen_to_map(mouse_pos.x, mouse_pos.y))
+            .filter(|map_pos| map.is_within_bounds(map_pos.x, map_pos.y));
+
+        if let Some(mouse_pos_map) = mouse_map_pos_opt {
             let res = astar_next_step(
                 &map,
                 player_res.cur_pos.clone(),
-                mouse_pos_map.to_position(),
+                mouse_pos_map,
+                &PathingProfile {
+                    opens_doors: true,
+                    digs: matches!(player_digger_query.get_single(), Ok(Some(_))),
+                    ..Default::default()
+                },
             );
 
             // pop the head if it's > 0, as it'll be the players pos
@@ -150,12 +289,11 @@ pub fn handle_mouse_movement(
     }
 
     for ele in (*pathfinding_history).iter() {
-        ctx.draw_glyph(
-            ele.x as usize,
-            ele.y as usize,
-            ScreenTilePriority::Tooltip,
-            |screen_tile| screen_tile.glyph.bg_color = Color::RED,
-        );
+        if let Some((x, y)) = viewport.map_to_screen(ele.x, ele.y) {
+            ctx.draw_glyph(x, y, ScreenTilePriority::Tooltip, |screen_tile| {
+                screen_tile.glyph.bg_color = Color::RED
+            });
+        }
     }
 
     if buttons.just_pressed(MouseButton::Left) {
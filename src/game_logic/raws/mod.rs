@@ -0,0 +1,146 @@
+use std::collections::HashMap;
+
+use bevy::prelude::*;
+use serde::Deserialize;
+
+use crate::utils::read_asset;
+
+use super::components::AiType;
+
+pub mod spawn_table;
+pub mod spawner;
+
+const MONSTER_RAWS_PATH: &str = "data/raws/monsters.json";
+const ITEM_RAWS_PATH: &str = "data/raws/items.json";
+
+#[derive(Clone, Debug, Deserialize)]
+#[serde(untagged)]
+pub enum RawGlyph {
+    Code(u16),
+    Char(char),
+}
+
+impl RawGlyph {
+    pub fn as_u16(&self) -> u16 {
+        match self {
+            RawGlyph::Code(code) => *code,
+            RawGlyph::Char(ch) => *ch as u16,
+        }
+    }
+}
+
+#[derive(Clone, Debug, Deserialize)]
+pub struct RawStats {
+    pub max_hp: i32,
+    pub power: i32,
+    pub defense: i32,
+}
+
+// one entry from a raws file, monsters and items share the same shape and just leave out the parts
+// that don't apply to them
+#[derive(Clone, Debug, Deserialize)]
+pub struct EntityRaw {
+    pub name: String,
+    pub glyph: RawGlyph,
+    pub fg: String,
+    #[serde(default = "default_bg")]
+    pub bg: String,
+    pub layer: f32,
+    pub blocks: Option<bool>,
+    pub stats: Option<RawStats>,
+    pub vision_range: Option<u16>,
+    pub ai: Option<AiType>,
+    #[serde(default)]
+    pub opens_doors: bool,
+    // monsters with this can dig, items with it let the player dig
+    pub dig_turns: Option<u32>,
+    #[serde(default)]
+    pub swims: bool,
+    #[serde(default)]
+    pub flies: bool,
+    #[serde(default)]
+    pub fire_immune: bool,
+    // inclusive range of dungeon depths this can turn up on
+    pub spawn_depth: (i32, i32),
+    pub spawn_weight: i32,
+}
+
+fn default_bg() -> String {
+    "000000".to_owned()
+}
+
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+pub enum RawKind {
+    Monster,
+    Item,
+}
+
+#[derive(Clone, Default)]
+pub struct RawMaster {
+    monsters: Vec<EntityRaw>,
+    items: Vec<EntityRaw>,
+    raws_by_name: HashMap<String, (RawKind, usize)>,
+}
+
+impl RawMaster {
+    pub fn load() -> RawMaster {
+        let mut raw_master = RawMaster {
+            monsters: load_raws_file(MONSTER_RAWS_PATH),
+            items: load_raws_file(ITEM_RAWS_PATH),
+            raws_by_name: HashMap::new(),
+        };
+
+        for (i, raw) in raw_master.monsters.iter().enumerate() {
+            raw_master
+                .raws_by_name
+                .insert(raw.name.clone(), (RawKind::Monster, i));
+        }
+
+        for (i, raw) in raw_master.items.iter().enumerate() {
+            if raw_master.raws_by_name.contains_key(&raw.name) {
+                warn!(
+                    "item raw {} shares a name with a monster, skipping",
+                    raw.name
+                );
+                continue;
+            }
+
+            raw_master
+                .raws_by_name
+                .insert(raw.name.clone(), (RawKind::Item, i));
+        }
+
+        raw_master
+    }
+
+    pub fn get(&self, name: &str) -> Option<(RawKind, &EntityRaw)> {
+        self.raws_by_name.get(name).map(|(kind, idx)| match kind {
+            RawKind::Monster => (*kind, &self.monsters[*idx]),
+            RawKind::Item => (*kind, &self.items[*idx]),
+        })
+    }
+
+    pub fn monsters(&self) -> &[EntityRaw] {
+        &self.monsters
+    }
+
+    pub fn items(&self) -> &[EntityRaw] {
+        &self.items
+    }
+}
+
+fn load_raws_file(path: &str) -> Vec<EntityRaw> {
+    match read_asset(path) {
+        Ok(raws_str) => match serde_json::from_str(&raws_str) {
+            Ok(raws) => raws,
+            Err(err) => {
+                warn!("could not parse {}, skipping it: {}", path, err);
+                Vec::new()
+            }
+        },
+        Err(err) => {
+            warn!("could not read raws, skipping them: {}", err);
+            Vec::new()
+        }
+    }
+}
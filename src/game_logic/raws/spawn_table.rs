@@ -0,0 +1,61 @@
+use crate::rng::GameRNG;
+
+use super::RawMaster;
+
+// weighted pick list, an entry with twice the weight turns up twice as often
+#[derive(Clone, Default)]
+pub struct SpawnTable {
+    entries: Vec<(String, i32)>,
+    total_weight: i32,
+}
+
+impl SpawnTable {
+    pub fn new() -> SpawnTable {
+        SpawnTable::default()
+    }
+
+    pub fn with_entry(mut self, name: &str, weight: i32) -> SpawnTable {
+        if weight > 0 {
+            self.entries.push((name.to_owned(), weight));
+            self.total_weight += weight;
+        }
+
+        self
+    }
+
+    pub fn is_empty(&self) -> bool {
+        self.total_weight == 0
+    }
+
+    pub fn roll(&self, rng: &mut GameRNG) -> Option<String> {
+        if self.is_empty() {
+            return None;
+        }
+
+        let mut roll = rng.rand_range(0..self.total_weight);
+
+        for (name, weight) in self.entries.iter() {
+            if roll < *weight {
+                return Some(name.clone());
+            }
+
+            roll -= weight;
+        }
+
+        None
+    }
+}
+
+impl RawMaster {
+    // everything that can turn up at this depth, entries get more common the deeper into their
+    // depth range the level is
+    pub fn spawn_table(&self, depth: i32) -> SpawnTable {
+        self.monsters()
+            .iter()
+            .chain(self.items().iter())
+            .filter(|raw| depth >= raw.spawn_depth.0 && depth <= raw.spawn_depth.1)
+            .fold(SpawnTable::new(), |table, raw| {
+                table.with_entry(&raw.name, raw.spawn_weight + (depth - raw.spawn_depth.0))
+            })
+    }
+}
@@ -0,0 +1,94 @@
+use std::collections::HashSet;
+
+use bevy::prelude::*;
+
+use crate::game_logic::components::{
+    Ai, Blocker, Digger, DiggingTool, DoorOpener, FireImmune, Flier, Item, Monster, Position,
+    Renderable, Stats, Swimmer, Viewshed,
+};
+
+use super::{RawKind, RawMaster};
+
+// builds an entity from the raw with the given name, returns None if there is no such raw
+pub fn spawn_named_entity(
+    commands: &mut Commands,
+    raws: &RawMaster,
+    name: &str,
+    position: Position,
+) -> Option<Entity> {
+    let (kind, raw) = match raws.get(name) {
+        Some(raw) => raw,
+        None => {
+            warn!("no raw named {}", name);
+            return None;
+        }
+    };
+
+    let mut entity_commands = commands.spawn();
+
+    entity_commands
+        .insert(Name::new(raw.name.clone()))
+        .insert(position)
+        .insert(Renderable {
+            glyph: raw.glyph.as_u16(),
+            fg: Color::hex(&raw.fg).unwrap_or(Color::WHITE),
+            bg: Color::hex(&raw.bg).unwrap_or(Color::BLACK),
+            layer: raw.layer,
+        });
+
+    match kind {
+        RawKind::Monster => entity_commands.insert(Monster {}),
+        RawKind::Item => entity_commands.insert(Item {}),
+    };
+
+    // monsters get in the way by default, items don't
+    if raw.blocks.unwrap_or(kind == RawKind::Monster) {
+        entity_commands.insert(Blocker {});
+    }
+
+    if let Some(stats) = &raw.stats {
+        entity_commands.insert(Stats {
+            max_hp: stats.max_hp,
+            hp: stats.max_hp,
+            power: stats.power,
+            defense: stats.defense,
+        });
+    }
+
+    if let Some(vision_range) = raw.vision_range {
+        entity_commands.insert(Viewshed {
+            dirty: true,
+            distance: vision_range,
+            visible_tiles: HashSet::new(),
+        });
+    }
+
+    if let Some(ai_type) = raw.ai {
+        entity_commands.insert(Ai { ai_type });
+    }
+
+    if raw.opens_doors {
+        entity_commands.insert(DoorOpener {});
+    }
+
+    if raw.swims {
+        entity_commands.insert(Swimmer {});
+    }
+
+    if raw.flies {
+        entity_commands.insert(Flier {});
+    }
+
+    if raw.fire_immune {
+        entity_commands.insert(FireImmune {});
+    }
+
+    if let Some(dig_turns) = raw.dig_turns {
+        match kind {
+            RawKind::Monster => entity_commands.insert(Digger::new(dig_turns)),
+            RawKind::Item => entity_commands.insert(DiggingTool { dig_turns }),
+        };
+    }
+
+    Some(entity_commands.id())
+}
@@ -1,19 +1,17 @@
-use std::collections::HashMap;
-
 use bevy::prelude::*;
 use iyes_loopless::prelude::*;
 
 use crate::{
     camera::MousePositionOnScreen,
-    screen::structs::{ScreenContext, ScreenTilePriority},
+    screen::{
+        structs::{ScreenContext, ScreenTilePriority},
+        viewport::MapViewport,
+    },
 };
 
 use super::{
     components::{Player, Position, Renderable, Viewshed},
-    map::{
-        game_map::{GameMap, GameTile},
-        pathfinding::astar_next_step,
-    },
+    map::{game_map::GameMap, pathfinding::astar_next_step, spatial_index::SpatialIndex},
     resources::PlayerResource,
 };
 
@@ -21,9 +19,11 @@ pub fn handle_renderable(
     mut _commands: Commands,
     mut ctx: ResMut<ScreenContext>,
     map: ResMut<GameMap>,
-    mut query: Query<(Entity, &Position, &Renderable)>,
+    query: Query<(Entity, &Position, &Renderable)>,
+    spatial_index: Res<SpatialIndex>,
     mut viewshed_visibility_query: Query<&Viewshed, With<Player>>,
     mut mouse_res: ResMut<MousePositionOnScreen>,
+    viewport: Res<MapViewport>,
 ) {
     //println!("renderable update");
 
@@ -38,37 +38,75 @@ pub fn handle_renderable(
         });
     }
 
-    let mut position_visibility_history: HashMap<Position, f32> = HashMap::new();
-
     let player_viewshed = viewshed_visibility_query.single();
 
-    //ctx.clear();
-
-    for (_entity, position, renderable) in query.iter_mut() {
-        let (x, y) = (position.x as usize, position.y as usize);
-
-        if !position_visibility_history.contains_key(position) {
-            position_visibility_history.insert(position.clone(), renderable.layer);
-        }
+    // terrain is drawn straight from the map, only actual entities go through the query below
+    for view_x in 0..viewport.width {
+        for view_y in 0..viewport.height {
+            let (x, y) = (viewport.screen_x + view_x, viewport.screen_y + view_y);
+            let map_pos = match viewport.screen_to_map(x as i32, y as i32) {
+                Some(map_pos) if map.is_within_bounds(map_pos.x, map_pos.y) => map_pos,
+                _ => {
+                    ctx.draw_glyph(x, y, ScreenTilePriority::Map, |screen_tile| {
+                        screen_tile.glyph.visible = false;
+                    });
+
+                    continue;
+                }
+            };
+
+            let idx = map.xy_idx_pos(&map_pos);
+            let game_tile = map.tiles[idx];
+            let is_visible = player_viewshed.visible_tiles.contains(&map_pos);
+
+            let tile_definition = game_tile.definition();
+
+            let glyph = if tile_definition.connects_walls {
+                smooth_wall_rendering(&map, map_pos.x, map_pos.y)
+            } else {
+                tile_definition.glyph
+            };
 
-        if let Some(last_layer) = position_visibility_history.get(position) {
-            if renderable.layer < *last_layer {
-                continue;
+            let (fg, bg) = if is_visible {
+                (tile_definition.fg, tile_definition.bg)
             } else {
-                position_visibility_history.insert(position.clone(), renderable.layer);
-            }
+                (greyscale(tile_definition.fg), greyscale(tile_definition.bg))
+            };
+
+            ctx.draw_glyph(x, y, ScreenTilePriority::Map, |screen_tile| {
+                screen_tile.glyph.visible = is_visible || map.viewed_tiles[idx];
+                screen_tile.glyph.char = glyph;
+                screen_tile.glyph.fg_color = fg;
+                screen_tile.glyph.bg_color = bg;
+                screen_tile.glyph.layer = 0.;
+            });
         }
+    }
 
-        let glyph = if renderable.glyph == GameTile::Wall.get_char_rep() {
-            smooth_wall_rendering(&map, position.x, position.y)
-        } else {
-            renderable.glyph
+    for (entity, position, renderable) in query.iter() {
+        let (x, y) = match viewport.map_to_screen(position.x, position.y) {
+            Some(screen_pos) => screen_pos,
+            None => continue,
         };
 
+        // only the top most renderable on a tile gets drawn
+        let is_covered = spatial_index
+            .entities_at(position.x, position.y)
+            .iter()
+            .filter(|other| **other != entity)
+            .any(|other| match query.get(*other) {
+                Ok((_, _, other_renderable)) => other_renderable.layer > renderable.layer,
+                Err(_) => false,
+            });
+
+        if is_covered {
+            continue;
+        }
+
         ctx.draw_glyph(x, y, ScreenTilePriority::Entity, |screen_tile| {
             screen_tile.glyph.visible = player_viewshed.visible_tiles.contains(position)
                 || map.viewed_tiles[map.xy_idx_pos(position)];
-            screen_tile.glyph.char = glyph;
+            screen_tile.glyph.char = renderable.glyph;
             screen_tile.glyph.layer = renderable.layer;
         });
 
@@ -78,22 +116,32 @@ pub fn handle_renderable(
                 screen_tile.glyph.bg_color = renderable.bg;
             });
         } else {
-            let linear_fg = renderable.fg.r() * 0.2126
-                + renderable.fg.g() * 0.7152
-                + renderable.fg.b() * 0.0722;
-
-            let linear_bg = renderable.bg.r() * 0.2126
-                + renderable.bg.g() * 0.7152
-                + renderable.bg.b() * 0.0722;
-
             ctx.draw_glyph(x, y, ScreenTilePriority::Entity, |screen_tile| {
-                screen_tile.glyph.fg_color = Color::rgb(linear_fg, linear_fg, linear_fg);
-                screen_tile.glyph.bg_color = Color::rgb(linear_bg, linear_bg, linear_bg);
+                screen_tile.glyph.fg_color = greyscale(renderable.fg);
+                screen_tile.glyph.bg_color = greyscale(renderable.bg);
             });
         }
     }
 }
 
+pub fn handle_viewport_follow(
+    map: Res<GameMap>,
+    player_res: Res<PlayerResource>,
+    mut viewport: ResMut<MapViewport>,
+) {
+    if !player_res.is_changed() && !map.is_changed() {
+        return;
+    }
+
+    viewport.centre_on(&player_res.cur_pos, map.width, map.height);
+}
+
+fn greyscale(colour: Color) -> Color {
+    let linear = colour.r() * 0.2126 + colour.g() * 0.7152 + colour.b() * 0.0722;
+
+    Color::rgb(linear, linear, linear)
+}
+
 fn smooth_wall_rendering(map: &GameMap, x: i32, y: i32) -> u16 {
     if x < 0 || x >= map.width as i32 || y < 0 || y >= map.height as i32 {
         return 35;
@@ -109,7 +157,7 @@ fn smooth_wall_rendering(map: &GameMap, x: i32, y: i32) -> u16 {
         } else {
             let idx = map.xy_idx(x, y);
 
-            map.viewed_tiles[idx] && map.tiles[idx] == GameTile::Wall
+            map.viewed_tiles[idx] && map.tiles[idx].definition().connects_walls
         }
     }
 
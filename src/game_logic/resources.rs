@@ -5,3 +5,11 @@ pub struct PlayerResource {
     pub cur_pos: Position,
     pub move_waypoints: Vec<Position>,
 }
+
+// which floor of the dungeon is loaded, and the seed it was built from. depth 0 is the town on
+// the surface and where everyone starts, the dungeon proper begins at 1
+#[derive(Default)]
+pub struct DungeonLevel {
+    pub depth: i32,
+    pub seed: u64,
+}
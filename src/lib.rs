@@ -0,0 +1,30 @@
+// the game itself is in main.rs, everything is in here so the map preview tool can use the map
+// generation without a window
+pub mod camera;
+pub mod game_logic;
+pub mod rng;
+pub mod screen;
+pub mod settings;
+pub mod sprites;
+pub mod utils;
+
+#[derive(Debug, Clone, Eq, PartialEq, Hash)]
+pub enum InGameState {
+    LoadMap,
+    AwaitingInput,
+    PlayerTurn,
+    EnemyTurn,
+}
+
+#[derive(Debug, Clone, Eq, PartialEq, Hash)]
+pub enum InMenuState {
+    MainMenu,
+}
+
+#[derive(Debug, Clone, Eq, PartialEq, Hash)]
+pub enum GameState {
+    LoadAssets,
+    Loading,
+    InMenu { menu_state: InMenuState },
+    InGame { game_state: InGameState },
+}
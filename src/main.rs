@@ -1,48 +1,32 @@
 use bevy::{prelude::*, window::PresentMode};
 use iyes_loopless::prelude::*;
 
-use rng::GameRNG;
-
-mod camera;
-mod game_logic;
-mod rng;
-mod screen;
-mod sprites;
-pub mod utils;
-
-#[derive(Debug, Clone, Eq, PartialEq, Hash)]
-pub enum InGameState {
-    LoadMap,
-    AwaitingInput,
-    PlayerTurn,
-    EnemyTurn,
-}
-
-#[derive(Debug, Clone, Eq, PartialEq, Hash)]
-pub enum InMenuState {
-    MainMenu,
-}
-
-#[derive(Debug, Clone, Eq, PartialEq, Hash)]
-pub enum GameState {
-    LoadAssets,
-    Loading,
-    InMenu { menu_state: InMenuState },
-    InGame { game_state: InGameState },
-}
+use rs_granite::{
+    camera, game_logic, rng::GameRNG, screen, settings::Settings, sprites,
+    sprites::tilesets::TilesetManifest, GameState,
+};
 
 fn main() {
     // When building for WASM, print panics to the browser console
     #[cfg(target_arch = "wasm32")]
     console_error_panic_hook::set_once();
 
+    let settings = Settings::load();
+    let tileset_manifest = TilesetManifest::load(&settings);
+    let sprite_sizes = tileset_manifest.sprite_sizes();
+
     App::new()
         .insert_resource(WindowDescriptor {
             // fill the entire browser window
             //fit_canvas_to_parent: true,
             //present_mode: PresentMode::AutoNoVsync,
+            width: settings.console_width as f32 * sprite_sizes.map_sprite_width,
+            height: settings.console_height as f32 * sprite_sizes.map_sprite_height,
+            resizable: true,
             ..default()
         })
+        .insert_resource(settings)
+        .insert_resource(tileset_manifest)
         .insert_resource(ClearColor(Color::BLACK))
         .insert_resource(GameRNG::new())
         .add_loopless_state(GameState::LoadAssets)
@@ -14,16 +14,31 @@ pub struct GameRNG {
 
 impl GameRNG {
     pub fn new() -> GameRNG {
+        GameRNG::seeded(rand::random::<u64>())
+    }
+
+    // the same seed always gives the same sequence, levels are built from one of these so they can
+    // be recreated
+    pub fn seeded(seed: u64) -> GameRNG {
         GameRNG {
-            rng_generator: rand_chacha::ChaCha8Rng::seed_from_u64(rand::random::<u64>()),
+            rng_generator: rand_chacha::ChaCha8Rng::seed_from_u64(seed),
             dice_regex: Regex::new("(\\d+)?d(\\d+)([\\+\\-]\\d+)?").unwrap(),
         }
     }
 
+    pub fn rand_u64(&mut self) -> u64 {
+        self.rng_generator.gen()
+    }
+
     pub fn rand_i32(&mut self) -> i32 {
         self.rng_generator.gen()
     }
 
+    // somewhere in 0..1
+    pub fn rand_f64(&mut self) -> f64 {
+        self.rng_generator.gen()
+    }
+
     pub fn rand_range(&mut self, range: Range<i32>) -> i32 {
         self.rng_generator.gen_range(range)
     }
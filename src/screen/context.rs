@@ -120,8 +120,10 @@ impl ScreenContext {
 
         let builder_vec = builder(ScreenTextBuilder::new()).build();
         // 16 / 8 = 2
-        let text_per_map_tile_width = self.sprite_sizes.map_sprite_width as usize
-            / self.sprite_sizes.text_sprite_width as usize;
+        // never less than one, a font wider than the map tiles still gets a glyph per tile
+        let text_per_map_tile_width = (self.sprite_sizes.map_sprite_width as usize
+            / self.sprite_sizes.text_sprite_width as usize)
+            .max(1);
 
         let mut char_vec = Vec::new();
 
@@ -161,7 +163,7 @@ impl ScreenContext {
         for mut screen_tile in self.screen_vec.iter_mut() {
             screen_tile.glyph.char = 0;
             screen_tile.glyph.fg_color = Color::BLACK;
-            screen_tile.glyph.fg_color = Color::BLACK;
+            screen_tile.glyph.bg_color = Color::BLACK;
             screen_tile.tile_text.clear();
             screen_tile.last_tile_priority = None;
         }
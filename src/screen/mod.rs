@@ -2,33 +2,54 @@ use std::collections::HashMap;
 
 use crate::{
     screen,
-    sprites::{SpriteAssets, SpriteSizes},
+    settings::Settings,
+    sprites::{tilesets::TilesetManifest, SpriteAssets},
     GameState,
 };
 use bevy::prelude::*;
 use iyes_loopless::prelude::*;
 
-use self::structs::ScreenContext;
+use self::{
+    structs::ScreenContext,
+    viewport::{MapViewport, UI_PANEL_HEIGHT},
+};
 
 pub mod context;
 mod render_screen;
 pub mod structs;
+pub mod viewport;
 
-pub(crate) struct ScreenPlugin;
+pub struct ScreenPlugin;
 
 impl Plugin for ScreenPlugin {
     fn build(&self, app: &mut App) {
+        let settings = app.world.resource::<Settings>().clone();
+        let (screen_width, screen_height) = (settings.console_width, settings.console_height);
+        let sprite_sizes = app.world.resource::<TilesetManifest>().sprite_sizes();
+
         app.insert_resource(ScreenContext::new(
-            80,
-            50,
-            SpriteSizes {
-                map_sprite_width: 16.,
-                map_sprite_height: 16.,
-                text_sprite_width: 8.,
-                text_sprite_height: 16.,
-            },
+            screen_width,
+            screen_height,
+            sprite_sizes,
+        ))
+        .insert_resource(MapViewport::new(
+            0,
+            UI_PANEL_HEIGHT,
+            screen_width,
+            screen_height - UI_PANEL_HEIGHT,
         ))
         .add_enter_system(GameState::Loading, render_screen::init_screen)
+        .add_system(
+            render_screen::rebuild_screen
+                .run_if(
+                    move |cur_state: Res<CurrentState<GameState>>| match cur_state.0 {
+                        GameState::InMenu { .. } => true,
+                        GameState::InGame { .. } => true,
+                        _ => false,
+                    },
+                )
+                .before("render_screen"),
+        )
         .add_system(
             render_screen::render_screen
                 .run_if(
@@ -10,19 +10,57 @@ pub fn init_screen(
     mut ctx: ResMut<ScreenContext>,
     sprite_sizes: Res<SpriteSizes>,
     sprite_assets: Res<SpriteAssets>,
-    mut windows: ResMut<Windows>,
 ) {
-    let primary_window = windows.primary_mut();
-    primary_window.set_resolution(
-        ctx.width as f32 * sprite_sizes.map_sprite_width,
-        ctx.height as f32 * sprite_sizes.map_sprite_height,
+    spawn_screen_sprites(
+        &mut commands,
+        ctx.as_mut(),
+        sprite_sizes.as_ref(),
+        sprite_assets.as_ref(),
     );
+}
+
+// a tileset or font swap changes the sprite sizes and atlases, so every sprite is thrown away and
+// made again. the next render_screen then treats every tile as changed
+pub fn rebuild_screen(
+    mut commands: Commands,
+    mut ctx: ResMut<ScreenContext>,
+    sprite_sizes: Res<SpriteSizes>,
+    sprite_assets: Res<SpriteAssets>,
+) {
+    if !sprite_assets.is_changed() || sprite_assets.is_added() {
+        return;
+    }
+
+    ctx.sprite_sizes = sprite_sizes.clone();
 
+    for screen_tile in ctx.screen_vec.iter_mut() {
+        for sprite_entity in screen_tile.sprite_entities.drain(..) {
+            commands.entity(sprite_entity).despawn_recursive();
+        }
+
+        screen_tile.rendered_glyph = None;
+        screen_tile.rendered_text.clear();
+    }
+
+    spawn_screen_sprites(
+        &mut commands,
+        ctx.as_mut(),
+        sprite_sizes.as_ref(),
+        sprite_assets.as_ref(),
+    );
+}
+
+fn spawn_screen_sprites(
+    commands: &mut Commands,
+    ctx: &mut ScreenContext,
+    sprite_sizes: &SpriteSizes,
+    sprite_assets: &SpriteAssets,
+) {
     for screen_tile in ctx.screen_vec.iter_mut() {
         let glyph_entity = create_sprite_entity(
-            &mut commands,
-            sprite_sizes.as_ref(),
-            sprite_assets.as_ref(),
+            commands,
+            sprite_sizes,
+            sprite_assets,
             &screen_tile.glyph,
             screen_tile.x,
             screen_tile.y,
@@ -39,111 +77,91 @@ pub fn render_screen(
     mut ctx: ResMut<ScreenContext>,
     sprite_sizes: Res<SpriteSizes>,
     sprite_assets: Res<SpriteAssets>,
-    mut query: Query<(
-        Entity,
-        &ScreenTilePos,
-        &mut Visibility,
-        &mut Transform,
-        &mut TextureAtlasSprite,
-        &mut Sprite,
-    )>,
+    mut query: Query<(&mut Visibility, &mut TextureAtlasSprite, &mut Sprite), With<ScreenTilePos>>,
 ) {
     if !ctx.is_changed() {
         return;
     }
 
-    // to check and create missing sprites
     for screen_tile in ctx.screen_vec.iter_mut() {
+        // skip anything that looks the same as it did last frame, this is the majority of the screen
+        if screen_tile.rendered_glyph.as_ref() == Some(&screen_tile.glyph)
+            && screen_tile.rendered_text == screen_tile.tile_text
+        {
+            continue;
+        }
+
         let text_count = screen_tile.tile_text.len() + 1;
         let entity_count = screen_tile.sprite_entities.len();
 
-        // situations
-        // text count = 1, no text defined, just the underlying glyph
-        // entity
+        // entity 0 is always the underlying glyph, anything after that is text
 
         if entity_count > text_count {
-            // if entity count is greater than text, then we need to delete entities
-            for i in 1..entity_count {
-                let text_entity = &screen_tile.sprite_entities[i];
-
-                commands.entity(*text_entity).despawn_recursive();
+            // if entity count is greater than text, then we need to delete the extra text entities
+            for text_entity in screen_tile.sprite_entities.drain(text_count..) {
+                commands.entity(text_entity).despawn_recursive();
             }
+        }
+
+        // update the entities that already exist, new ones are created with the right glyph
+        let existing_count = screen_tile.sprite_entities.len();
+        let has_text_sprites = text_count > 1;
 
-            screen_tile.sprite_entities.truncate(1);
-        } else if entity_count < text_count {
-            // if entity count is less than text count, we need to create new text entities
-            for i in 0..(text_count - 1) {
-                let text_entry = &screen_tile.tile_text[i];
-
-                let glyph_entity = create_sprite_entity(
-                    &mut commands,
-                    sprite_sizes.as_ref(),
-                    sprite_assets.as_ref(),
-                    text_entry,
-                    screen_tile.x,
-                    screen_tile.y,
-                    true,
-                    i as f32 * sprite_sizes.text_sprite_width,
-                );
-
-                screen_tile.sprite_entities.push(glyph_entity);
+        // a freshly spawned base entity can't be queried until next frame, so it gets retried then
+        let base_updated = if let Ok((mut visibility, mut fg_sprite, mut bg_sprite)) =
+            query.get_mut(screen_tile.sprite_entities[0])
+        {
+            if has_text_sprites {
+                visibility.is_visible = false;
+            } else {
+                visibility.is_visible = screen_tile.glyph.visible;
+                fg_sprite.index = sprite_assets.map_index(screen_tile.glyph.char);
+                fg_sprite.color = screen_tile.glyph.fg_color;
+                bg_sprite.color = screen_tile.glyph.bg_color;
             }
-        }
-    }
 
-    for (entity, screen_tile_pos, mut visibility, mut transform, mut fg_sprite, mut bg_sprite) in
-        query.iter_mut()
-    {
-        let screen_tile = ctx.get_tile_mut(screen_tile_pos.x, screen_tile_pos.y);
-
-        // the below block of code, as well as the backwards entity lookup is hideously slow
-
-        let transform_position_x = (screen_tile.x * sprite_sizes.map_sprite_width as usize) as f32
-            + (sprite_sizes.map_sprite_width / 2.);
-        let transform_position_y = (screen_tile.y * sprite_sizes.map_sprite_height as usize) as f32
-            + (sprite_sizes.map_sprite_height / 2.);
-
-        // if the screen_tile has more than one entry, there's the base tile plus text
-        let has_text_sprites = screen_tile.sprite_entities.len() > 1;
-        // if the current entity is not in position 0, ie not the base tile, then it's text
-        let is_entity_text = if has_text_sprites {
-            screen_tile
-                .sprite_entities
-                .iter()
-                .position(|e| e == &entity)
-                .unwrap()
-                > 0
+            true
         } else {
             false
         };
 
-        let text_count = screen_tile.tile_text.len() + 1;
-        let entity_count = screen_tile.sprite_entities.len();
-
-        if has_text_sprites && is_entity_text && text_count == entity_count {
-            visibility.is_visible = true;
+        for i in 1..existing_count {
+            let text_info = &screen_tile.tile_text[i - 1];
 
-            let text_entity_pos = screen_tile
-                .sprite_entities
-                .iter()
-                .position(|e| e == &entity)
-                .unwrap();
+            if let Ok((mut visibility, mut fg_sprite, mut bg_sprite)) =
+                query.get_mut(screen_tile.sprite_entities[i])
+            {
+                visibility.is_visible = true;
+                fg_sprite.index = sprite_assets.text_index(text_info.char);
+                fg_sprite.color = text_info.fg_color;
+                bg_sprite.color = text_info.bg_color;
+            }
+        }
 
-            let text_info = &screen_tile.tile_text[text_entity_pos - 1];
+        // if entity count is less than text count, we need to create the missing text entities
+        for i in existing_count..text_count {
+            let text_entry = &screen_tile.tile_text[i - 1];
+
+            let glyph_entity = create_sprite_entity(
+                &mut commands,
+                sprite_sizes.as_ref(),
+                sprite_assets.as_ref(),
+                text_entry,
+                screen_tile.x,
+                screen_tile.y,
+                true,
+                (i - 1) as f32 * sprite_sizes.text_sprite_width,
+            );
+
+            screen_tile.sprite_entities.push(glyph_entity);
+        }
 
-            fg_sprite.index = text_info.char as usize;
-            fg_sprite.color = text_info.fg_color;
-            bg_sprite.color = text_info.bg_color;
-        } else if !is_entity_text && has_text_sprites {
-            visibility.is_visible = false;
+        screen_tile.rendered_glyph = if base_updated {
+            Some(screen_tile.glyph.clone())
         } else {
-            visibility.is_visible = screen_tile.glyph.visible;
-            fg_sprite.index = screen_tile.glyph.char as usize;
-            transform.translation.x = transform_position_x;
-            transform.translation.y = transform_position_y;
-            fg_sprite.color = screen_tile.glyph.fg_color;
-            bg_sprite.color = screen_tile.glyph.bg_color;
-        }
+            None
+        };
+        screen_tile.rendered_text = screen_tile.tile_text.clone();
     }
 
     ctx.clear();
@@ -176,7 +194,11 @@ fn create_sprite_entity(
     let transform_position_y = (y * sprite_sizes.map_sprite_height as usize) as f32
         + (sprite_sizes.map_sprite_height / 2.);
 
-    let mut char_sprite = TextureAtlasSprite::new(glyph.char.into());
+    let mut char_sprite = TextureAtlasSprite::new(if is_text {
+        sprite_assets.text_index(glyph.char)
+    } else {
+        sprite_assets.map_index(glyph.char)
+    });
     char_sprite.color = glyph.fg_color;
 
     commands
@@ -3,7 +3,7 @@ use iyes_loopless::prelude::*;
 
 use crate::sprites::SpriteSizes;
 
-#[derive(Clone, Default, Debug)]
+#[derive(Clone, Default, Debug, PartialEq)]
 pub struct ScreenGlyph {
     pub char: u16,
     pub fg_color: Color,
@@ -28,6 +28,9 @@ pub struct ScreenTile {
     pub glyph: ScreenGlyph,
     pub(in crate::screen) tile_text: Vec<ScreenGlyph>,
     pub(in crate::screen) sprite_entities: Vec<Entity>,
+    // what was last pushed to the sprite entities, used to skip tiles that haven't changed
+    pub(in crate::screen) rendered_glyph: Option<ScreenGlyph>,
+    pub(in crate::screen) rendered_text: Vec<ScreenGlyph>,
 }
 
 #[derive(Clone)]
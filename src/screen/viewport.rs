@@ -0,0 +1,70 @@
+use crate::game_logic::components::Position;
+
+// rows kept free along the bottom of the screen for ui panels, the map is never drawn there
+pub const UI_PANEL_HEIGHT: usize = 7;
+
+// the part of the screen the map is drawn into, and which part of the map that currently shows
+#[derive(Clone, Debug)]
+pub struct MapViewport {
+    pub screen_x: usize,
+    pub screen_y: usize,
+    pub width: usize,
+    pub height: usize,
+    pub map_x: i32,
+    pub map_y: i32,
+}
+
+impl MapViewport {
+    pub fn new(screen_x: usize, screen_y: usize, width: usize, height: usize) -> MapViewport {
+        MapViewport {
+            screen_x,
+            screen_y,
+            width,
+            height,
+            map_x: 0,
+            map_y: 0,
+        }
+    }
+
+    pub fn map_to_screen(&self, x: i32, y: i32) -> Option<(usize, usize)> {
+        let view_x = x - self.map_x;
+        let view_y = y - self.map_y;
+
+        if view_x < 0 || view_y < 0 || view_x >= self.width as i32 || view_y >= self.height as i32 {
+            return None;
+        }
+
+        Some((
+            self.screen_x + view_x as usize,
+            self.screen_y + view_y as usize,
+        ))
+    }
+
+    pub fn screen_to_map(&self, x: i32, y: i32) -> Option<Position> {
+        let view_x = x - self.screen_x as i32;
+        let view_y = y - self.screen_y as i32;
+
+        if view_x < 0 || view_y < 0 || view_x >= self.width as i32 || view_y >= self.height as i32 {
+            return None;
+        }
+
+        Some(Position {
+            x: self.map_x + view_x,
+            y: self.map_y + view_y,
+        })
+    }
+
+    pub fn centre_on(&mut self, pos: &Position, map_width: usize, map_height: usize) {
+        fn clamp_axis(centre: i32, view_size: usize, map_size: usize) -> i32 {
+            if map_size <= view_size {
+                // the whole map fits, so keep it in the middle of the viewport
+                return -((view_size - map_size) as i32 / 2);
+            }
+
+            (centre - view_size as i32 / 2).clamp(0, (map_size - view_size) as i32)
+        }
+
+        self.map_x = clamp_axis(pos.x, self.width, map_width);
+        self.map_y = clamp_axis(pos.y, self.height, map_height);
+    }
+}
@@ -0,0 +1,82 @@
+use serde::Deserialize;
+
+use crate::{screen::viewport::UI_PANEL_HEIGHT, utils::read_asset};
+
+const SETTINGS_PATH: &str = "settings.json";
+// anything smaller leaves no room for the map beside the ui panel
+const MIN_CONSOLE_WIDTH: usize = 20;
+const MIN_CONSOLE_HEIGHT: usize = UI_PANEL_HEIGHT + 10;
+
+#[derive(Clone, Debug, Deserialize)]
+#[serde(default)]
+pub struct Settings {
+    pub console_width: usize,
+    pub console_height: usize,
+    // names of entries in the tileset manifest, sprite sizes come from whichever is picked
+    pub map_tileset: String,
+    pub text_font: String,
+    // plays back each step of map generation before the level starts, on by default in debug
+    // builds only
+    pub visualise_map_generation: bool,
+}
+
+impl Default for Settings {
+    fn default() -> Self {
+        Settings {
+            console_width: 80,
+            console_height: 50,
+            map_tileset: "RogueYun".to_owned(),
+            text_font: "VGA".to_owned(),
+            visualise_map_generation: cfg!(debug_assertions),
+        }
+    }
+}
+
+impl Settings {
+    // anything missing or unreadable falls back to the defaults, a broken settings file shouldn't
+    // stop the game from starting. this runs before logging is set up, hence eprintln
+    pub fn load() -> Settings {
+        let settings_str = match Settings::read_file() {
+            Ok(settings_str) => settings_str,
+            Err(err) => {
+                eprintln!("could not read settings, using defaults: {}", err);
+                return Settings::default();
+            }
+        };
+
+        match Settings::parse(&settings_str) {
+            Ok(settings) => settings,
+            Err(err) => {
+                eprintln!("{}, using defaults", err);
+                Settings::default()
+            }
+        }
+    }
+
+    // the settings file as it is on disk right now, also used to notice it being edited while the
+    // game is running
+    pub fn read_file() -> Result<String, String> {
+        read_asset(SETTINGS_PATH)
+    }
+
+    pub fn parse(settings_str: &str) -> Result<Settings, String> {
+        serde_json::from_str::<Settings>(settings_str)
+            .map(Settings::clamped)
+            .map_err(|err| format!("could not parse {}: {}", SETTINGS_PATH, err))
+    }
+
+    // a console too small to fit the ui is bumped up to the smallest one that does
+    fn clamped(mut self) -> Settings {
+        if self.console_width < MIN_CONSOLE_WIDTH || self.console_height < MIN_CONSOLE_HEIGHT {
+            eprintln!(
+                "a {}x{} console is too small, using at least {}x{}",
+                self.console_width, self.console_height, MIN_CONSOLE_WIDTH, MIN_CONSOLE_HEIGHT
+            );
+
+            self.console_width = self.console_width.max(MIN_CONSOLE_WIDTH);
+            self.console_height = self.console_height.max(MIN_CONSOLE_HEIGHT);
+        }
+
+        self
+    }
+}
@@ -1,51 +1,56 @@
+use std::collections::HashMap;
+
 use bevy::prelude::*;
-use bevy_asset_loader::prelude::{AssetCollection, LoadingState, LoadingStateAppExt};
 use iyes_loopless::prelude::*;
 
 use crate::GameState;
 
-pub(crate) struct SpritePlugin;
+use self::tilesets::TilesetManifest;
+
+pub mod tilesets;
+
+pub struct SpritePlugin;
 
 impl Plugin for SpritePlugin {
     fn build(&self, app: &mut App) {
-        app.insert_resource(SpriteSizes {
-            map_sprite_width: 16.,
-            map_sprite_height: 16.,
-            text_sprite_width: 8.,
-            text_sprite_height: 16.,
-        })
-        .add_loading_state(
-            LoadingState::new(GameState::LoadAssets)
-                .continue_to_state(GameState::Loading)
-                .with_collection::<SpriteAssets>(),
-        )
-        .add_enter_system(GameState::Loading, setup_sprites);
+        let sprite_sizes = app.world.resource::<TilesetManifest>().sprite_sizes();
+
+        app.insert_resource(sprite_sizes)
+            .add_startup_system(tilesets::load_sprite_assets)
+            .add_system(tilesets::check_sprite_assets_loaded.run_in_state(GameState::LoadAssets))
+            .add_system(tilesets::watch_settings_file.run_if(sprites_loaded))
+            .add_system(tilesets::handle_tileset_switching.run_if(sprites_loaded))
+            .add_enter_system(GameState::Loading, setup_sprites);
+
+        if cfg!(debug_assertions) {
+            app.add_system(tilesets::handle_tileset_hotkeys.run_if(sprites_loaded));
+        }
     }
 }
 
-#[derive(AssetCollection)]
+fn sprites_loaded(cur_state: Res<CurrentState<GameState>>) -> bool {
+    matches!(
+        cur_state.0,
+        GameState::InMenu { .. } | GameState::InGame { .. }
+    )
+}
+
+// built from the tileset manifest rather than being fixed at compile time, so it can be swapped out
 pub struct SpriteAssets {
-    #[asset(texture_atlas(
-        tile_size_x = 16.,
-        tile_size_y = 16.,
-        columns = 16,
-        rows = 16,
-        padding_x = 0.,
-        padding_y = 0.
-    ))]
-    #[asset(path = "sprites/map/16x16-RogueYun-AgmEdit.png")]
     pub map_tex_atlas: Handle<TextureAtlas>,
-
-    #[asset(texture_atlas(
-        tile_size_x = 8.,
-        tile_size_y = 16.,
-        columns = 16,
-        rows = 16,
-        padding_x = 0.,
-        padding_y = 0.
-    ))]
-    #[asset(path = "sprites/text/8x16-VGA.png")]
     pub text_tex_atlas: Handle<TextureAtlas>,
+    pub map_glyph_map: HashMap<u16, usize>,
+    pub text_glyph_map: HashMap<u16, usize>,
+}
+
+impl SpriteAssets {
+    pub fn map_index(&self, glyph: u16) -> usize {
+        *self.map_glyph_map.get(&glyph).unwrap_or(&(glyph as usize))
+    }
+
+    pub fn text_index(&self, glyph: u16) -> usize {
+        *self.text_glyph_map.get(&glyph).unwrap_or(&(glyph as usize))
+    }
 }
 
 #[derive(Clone)]
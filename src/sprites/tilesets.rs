@@ -0,0 +1,325 @@
+use std::collections::HashMap;
+
+use bevy::{asset::LoadState, prelude::*};
+use iyes_loopless::prelude::*;
+use serde::Deserialize;
+
+use crate::{settings::Settings, utils::read_asset, GameState};
+
+use super::{SpriteAssets, SpriteSizes};
+
+const TILESET_MANIFEST_PATH: &str = "tilesets.json";
+const SETTINGS_CHECK_SECONDS: f32 = 1.0;
+
+#[derive(Clone, Debug, Deserialize)]
+pub struct TilesetDefinition {
+    pub name: String,
+    pub path: String,
+    pub tile_width: f32,
+    pub tile_height: f32,
+    pub columns: usize,
+    pub rows: usize,
+    #[serde(default)]
+    pub padding_x: f32,
+    #[serde(default)]
+    pub padding_y: f32,
+    // glyph -> atlas index, for sheets that aren't laid out in code page 437 order
+    #[serde(default)]
+    pub glyph_map: HashMap<u16, usize>,
+}
+
+#[derive(Clone, Debug, Deserialize)]
+pub struct TilesetManifest {
+    pub map_tilesets: Vec<TilesetDefinition>,
+    pub text_fonts: Vec<TilesetDefinition>,
+    #[serde(skip)]
+    pub current_map_tileset: usize,
+    #[serde(skip)]
+    pub current_text_font: usize,
+}
+
+impl Default for TilesetManifest {
+    fn default() -> Self {
+        TilesetManifest {
+            map_tilesets: vec![TilesetDefinition {
+                name: "RogueYun".to_owned(),
+                path: "sprites/map/16x16-RogueYun-AgmEdit.png".to_owned(),
+                tile_width: 16.,
+                tile_height: 16.,
+                columns: 16,
+                rows: 16,
+                padding_x: 0.,
+                padding_y: 0.,
+                glyph_map: HashMap::new(),
+            }],
+            text_fonts: vec![TilesetDefinition {
+                name: "VGA".to_owned(),
+                path: "sprites/text/8x16-VGA.png".to_owned(),
+                tile_width: 8.,
+                tile_height: 16.,
+                columns: 16,
+                rows: 16,
+                padding_x: 0.,
+                padding_y: 0.,
+                glyph_map: HashMap::new(),
+            }],
+            current_map_tileset: 0,
+            current_text_font: 0,
+        }
+    }
+}
+
+impl TilesetManifest {
+    // same deal as the settings, a missing or broken manifest falls back to the built in sheets
+    pub fn load(settings: &Settings) -> TilesetManifest {
+        let mut manifest = match read_asset(TILESET_MANIFEST_PATH) {
+            Ok(manifest_str) => match serde_json::from_str::<TilesetManifest>(&manifest_str) {
+                Ok(manifest)
+                    if !manifest.map_tilesets.is_empty() && !manifest.text_fonts.is_empty() =>
+                {
+                    manifest
+                }
+                Ok(_) => {
+                    eprintln!(
+                        "{} needs at least one tileset and font, using defaults",
+                        TILESET_MANIFEST_PATH
+                    );
+                    TilesetManifest::default()
+                }
+                Err(err) => {
+                    eprintln!(
+                        "could not parse {}, using defaults: {}",
+                        TILESET_MANIFEST_PATH, err
+                    );
+                    TilesetManifest::default()
+                }
+            },
+            Err(err) => {
+                eprintln!(
+                    "could not read the tileset manifest, using defaults: {}",
+                    err
+                );
+                TilesetManifest::default()
+            }
+        };
+
+        manifest.select(settings);
+
+        manifest
+    }
+
+    // points at whichever tileset and font the settings name, the first of each if they aren't in
+    // the manifest. returns whether that's different to what was picked before
+    pub fn select(&mut self, settings: &Settings) -> bool {
+        let map_tileset = self
+            .map_tilesets
+            .iter()
+            .position(|tileset| tileset.name == settings.map_tileset)
+            .unwrap_or(0);
+
+        let text_font = self
+            .text_fonts
+            .iter()
+            .position(|font| font.name == settings.text_font)
+            .unwrap_or(0);
+
+        let changed =
+            map_tileset != self.current_map_tileset || text_font != self.current_text_font;
+
+        self.current_map_tileset = map_tileset;
+        self.current_text_font = text_font;
+
+        changed
+    }
+
+    pub fn map_tileset(&self) -> &TilesetDefinition {
+        &self.map_tilesets[self.current_map_tileset]
+    }
+
+    pub fn text_font(&self) -> &TilesetDefinition {
+        &self.text_fonts[self.current_text_font]
+    }
+
+    pub fn sprite_sizes(&self) -> SpriteSizes {
+        SpriteSizes {
+            map_sprite_width: self.map_tileset().tile_width,
+            map_sprite_height: self.map_tileset().tile_height,
+            text_sprite_width: self.text_font().tile_width,
+            text_sprite_height: self.text_font().tile_height,
+        }
+    }
+}
+
+// images that have been asked for but might not be on the gpu yet
+#[derive(Default)]
+pub struct TilesetImageHandles(Vec<Handle<Image>>);
+
+fn build_texture_atlas(
+    asset_server: &AssetServer,
+    texture_atlases: &mut Assets<TextureAtlas>,
+    image_handles: &mut TilesetImageHandles,
+    tileset: &TilesetDefinition,
+) -> Handle<TextureAtlas> {
+    let image_handle: Handle<Image> = asset_server.load(tileset.path.as_str());
+
+    image_handles.0.push(image_handle.clone());
+
+    texture_atlases.add(TextureAtlas::from_grid_with_padding(
+        image_handle,
+        Vec2::new(tileset.tile_width, tileset.tile_height),
+        tileset.columns,
+        tileset.rows,
+        Vec2::new(tileset.padding_x, tileset.padding_y),
+        Vec2::ZERO,
+    ))
+}
+
+fn build_sprite_assets(
+    asset_server: &AssetServer,
+    texture_atlases: &mut Assets<TextureAtlas>,
+    image_handles: &mut TilesetImageHandles,
+    manifest: &TilesetManifest,
+) -> SpriteAssets {
+    SpriteAssets {
+        map_tex_atlas: build_texture_atlas(
+            asset_server,
+            texture_atlases,
+            image_handles,
+            manifest.map_tileset(),
+        ),
+        text_tex_atlas: build_texture_atlas(
+            asset_server,
+            texture_atlases,
+            image_handles,
+            manifest.text_font(),
+        ),
+        map_glyph_map: manifest.map_tileset().glyph_map.clone(),
+        text_glyph_map: manifest.text_font().glyph_map.clone(),
+    }
+}
+
+pub fn load_sprite_assets(
+    mut commands: Commands,
+    asset_server: Res<AssetServer>,
+    mut texture_atlases: ResMut<Assets<TextureAtlas>>,
+    manifest: Res<TilesetManifest>,
+) {
+    let mut image_handles = TilesetImageHandles::default();
+
+    commands.insert_resource(build_sprite_assets(
+        asset_server.as_ref(),
+        texture_atlases.as_mut(),
+        &mut image_handles,
+        manifest.as_ref(),
+    ));
+    commands.insert_resource(image_handles);
+}
+
+pub fn check_sprite_assets_loaded(
+    mut commands: Commands,
+    asset_server: Res<AssetServer>,
+    image_handles: Res<TilesetImageHandles>,
+) {
+    match asset_server.get_group_load_state(image_handles.0.iter().map(|handle| handle.id)) {
+        LoadState::Loaded => commands.insert_resource(NextState(GameState::Loading)),
+        LoadState::Failed => panic!("failed to load tileset images"),
+        _ => {}
+    }
+}
+
+// the tileset and font follow the settings, the screen picks up the new sizes and rebuilds its
+// sprites when it sees the sprite assets change
+pub fn handle_tileset_switching(
+    settings: Res<Settings>,
+    asset_server: Res<AssetServer>,
+    mut texture_atlases: ResMut<Assets<TextureAtlas>>,
+    mut image_handles: ResMut<TilesetImageHandles>,
+    mut manifest: ResMut<TilesetManifest>,
+    mut sprite_assets: ResMut<SpriteAssets>,
+    mut sprite_sizes: ResMut<SpriteSizes>,
+) {
+    if !settings.is_changed() || !manifest.select(&settings) {
+        return;
+    }
+
+    info!(
+        "switching to tileset {}, font {}",
+        manifest.map_tileset().name,
+        manifest.text_font().name
+    );
+
+    // the old atlases are dropped along with the old sprite assets
+    image_handles.0.clear();
+
+    *sprite_assets = build_sprite_assets(
+        asset_server.as_ref(),
+        texture_atlases.as_mut(),
+        image_handles.as_mut(),
+        manifest.as_ref(),
+    );
+    *sprite_sizes = manifest.sprite_sizes();
+}
+
+// settings.json is checked for edits while the game runs, so changing the tileset or font there
+// takes effect straight away. the console size still needs a restart
+pub fn watch_settings_file(
+    time: Res<Time>,
+    mut since_check: Local<f32>,
+    mut last_read: Local<Option<String>>,
+    mut settings: ResMut<Settings>,
+) {
+    *since_check += time.delta_seconds();
+
+    if *since_check < SETTINGS_CHECK_SECONDS {
+        return;
+    }
+
+    *since_check = 0.0;
+
+    let settings_str = match Settings::read_file() {
+        Ok(settings_str) => settings_str,
+        Err(_) => return,
+    };
+
+    if last_read.as_ref() == Some(&settings_str) {
+        return;
+    }
+
+    let new_settings = Settings::parse(&settings_str);
+
+    *last_read = Some(settings_str);
+
+    let new_settings = match new_settings {
+        Ok(new_settings) => new_settings,
+        Err(err) => {
+            warn!("{}, keeping the current tileset and font", err);
+            return;
+        }
+    };
+
+    // only touched when they differ, any change to the settings makes the sprites get rebuilt
+    if settings.map_tileset != new_settings.map_tileset
+        || settings.text_font != new_settings.text_font
+    {
+        settings.map_tileset = new_settings.map_tileset;
+        settings.text_font = new_settings.text_font;
+    }
+}
+
+// debug builds only, a quicker way to flip through them while working on tilesets. F2 cycles the
+// map tileset and F3 cycles the text font by changing the settings
+pub fn handle_tileset_hotkeys(
+    keyboard: Res<Input<KeyCode>>,
+    manifest: Res<TilesetManifest>,
+    mut settings: ResMut<Settings>,
+) {
+    if keyboard.just_pressed(KeyCode::F2) {
+        let next = (manifest.current_map_tileset + 1) % manifest.map_tilesets.len();
+
+        settings.map_tileset = manifest.map_tilesets[next].name.clone();
+    } else if keyboard.just_pressed(KeyCode::F3) {
+        let next = (manifest.current_text_font + 1) % manifest.text_fonts.len();
+
+        settings.text_font = manifest.text_fonts[next].name.clone();
+    }
+}
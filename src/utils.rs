@@ -1,3 +1,5 @@
+use std::path::PathBuf;
+
 use crate::game_logic::components::Position;
 
 #[derive(Clone, Debug)]
@@ -12,7 +14,10 @@ impl Point {
     }
 
     pub fn to_position(&self) -> Position {
-        Position { x: self.x, y: self.y }
+        Position {
+            x: self.x,
+            y: self.y,
+        }
     }
 }
 
@@ -37,6 +42,13 @@ impl Rectangle {
         ((self.x + self.width / 2), (self.y + self.height / 2))
     }
 
+    pub fn intersects(&self, other: &Rectangle) -> bool {
+        self.x < other.x + other.width
+            && other.x < self.x + self.width
+            && self.y < other.y + other.height
+            && other.y < self.y + self.height
+    }
+
     pub fn all_squares(&self) -> Vec<(usize, usize)> {
         let mut res = Vec::new();
 
@@ -49,3 +61,99 @@ impl Rectangle {
         res
     }
 }
+
+// every data file the game reads is also baked into the binary, the web build has no filesystem
+// to read them from. new prefabs need adding here as well as to the folder
+const EMBEDDED_ASSETS: &[(&str, &str)] = &[
+    ("settings.json", include_str!("../assets/settings.json")),
+    ("tilesets.json", include_str!("../assets/tilesets.json")),
+    ("data/tiles.json", include_str!("../assets/data/tiles.json")),
+    (
+        "data/map_recipes.json",
+        include_str!("../assets/data/map_recipes.json"),
+    ),
+    (
+        "data/raws/monsters.json",
+        include_str!("../assets/data/raws/monsters.json"),
+    ),
+    (
+        "data/raws/items.json",
+        include_str!("../assets/data/raws/items.json"),
+    ),
+    (
+        "data/prefabs/boss_arena.txt",
+        include_str!("../assets/data/prefabs/boss_arena.txt"),
+    ),
+    (
+        "data/prefabs/goblin_camp.txt",
+        include_str!("../assets/data/prefabs/goblin_camp.txt"),
+    ),
+    (
+        "data/prefabs/rat_nest.txt",
+        include_str!("../assets/data/prefabs/rat_nest.txt"),
+    ),
+    (
+        "data/prefabs/treasure_vault.txt",
+        include_str!("../assets/data/prefabs/treasure_vault.txt"),
+    ),
+];
+
+// mirrors where bevy looks for the assets folder, so data files can be read outside the asset server
+pub fn asset_path(path: &str) -> PathBuf {
+    let base_path = match std::env::var("CARGO_MANIFEST_DIR") {
+        Ok(manifest_dir) => PathBuf::from(manifest_dir),
+        Err(_) => std::env::current_exe()
+            .ok()
+            .and_then(|exe_path| exe_path.parent().map(|p| p.to_owned()))
+            .unwrap_or_default(),
+    };
+
+    base_path.join("assets").join(path)
+}
+
+// the assets folder wins so data can be tweaked without a rebuild, the baked in copy is used when
+// the file can't be read, which on the web is always
+pub fn read_asset(path: &str) -> Result<String, String> {
+    #[cfg(not(target_arch = "wasm32"))]
+    if let Ok(asset_str) = std::fs::read_to_string(asset_path(path)) {
+        return Ok(asset_str);
+    }
+
+    EMBEDDED_ASSETS
+        .iter()
+        .find(|(embedded_path, _)| *embedded_path == path)
+        .map(|(_, asset_str)| asset_str.to_string())
+        .ok_or_else(|| format!("{} isn't in the assets folder or built in", path))
+}
+
+// paths of every file in an assets folder with the given extension, sorted so they always come
+// back in the same order. falls back to the baked in files the same way read_asset does
+pub fn list_assets(dir: &str, extension: &str) -> Vec<String> {
+    #[cfg(not(target_arch = "wasm32"))]
+    if let Ok(entries) = std::fs::read_dir(asset_path(dir)) {
+        let mut paths: Vec<String> = entries
+            .filter_map(|entry| entry.ok().map(|entry| entry.path()))
+            .filter(|path| path.extension().is_some_and(|ext| ext == extension))
+            .filter_map(|path| {
+                path.file_name()
+                    .map(|name| format!("{}/{}", dir, name.to_string_lossy()))
+            })
+            .collect();
+
+        paths.sort();
+
+        return paths;
+    }
+
+    let suffix = format!(".{}", extension);
+    let mut paths: Vec<String> = EMBEDDED_ASSETS
+        .iter()
+        .map(|(path, _)| *path)
+        .filter(|path| path.starts_with(dir) && path.ends_with(&suffix))
+        .map(str::to_owned)
+        .collect();
+
+    paths.sort();
+
+    paths
+}